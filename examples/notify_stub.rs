@@ -0,0 +1,102 @@
+//! A stand-in desktop notification server, for trying signal-tui's
+//! notifications on a private session bus:
+//!
+//!     dbus-run-session -- sh -c \
+//!         'cargo run --example notify_stub -- --invoke reply >notify.log & cargo run'
+//!
+//! Every notification is printed as one line. With `--invoke <action>`
+//! ("default" or "reply"), that action is invoked a second after each
+//! notification, as if it had been clicked.
+
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use zbus::blocking::Connection;
+use zbus::interface;
+use zbus::zvariant::Value;
+
+const PATH: &str = "/org/freedesktop/Notifications";
+
+struct Stub {
+    next_id: u32,
+    invoke: Option<String>,
+}
+
+#[interface(name = "org.freedesktop.Notifications")]
+impl Stub {
+    fn get_capabilities(&self) -> Vec<&str> {
+        vec!["actions", "body"]
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &mut self,
+        #[zbus(connection)] conn: &zbus::Connection,
+        app_name: &str,
+        replaces_id: u32,
+        _app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: Vec<&str>,
+        _hints: std::collections::HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> u32 {
+        let id = if replaces_id != 0 {
+            replaces_id
+        } else {
+            self.next_id += 1;
+            self.next_id
+        };
+        println!(
+            "notify id={id} replaces={replaces_id} app={app_name:?} summary={summary:?} body={body:?} \
+             actions={actions:?} timeout={expire_timeout}"
+        );
+        if let Some(action) = self.invoke.clone()
+            && actions.contains(&action.as_str())
+        {
+            let conn = Connection::from(conn.clone());
+            thread::spawn(move || {
+                thread::sleep(Duration::from_secs(1));
+                println!("invoke id={id} action={action:?}");
+                let _ = conn.emit_signal(
+                    None::<()>,
+                    PATH,
+                    "org.freedesktop.Notifications",
+                    "ActionInvoked",
+                    &(id, action),
+                );
+            });
+        }
+        id
+    }
+
+    fn close_notification(&self, id: u32) {
+        println!("close id={id}");
+    }
+
+    fn get_server_information(&self) -> (&str, &str, &str, &str) {
+        ("notify_stub", "signal-tui", "0", "1.2")
+    }
+}
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let mut invoke = None;
+    while let Some(a) = args.next() {
+        match a.as_str() {
+            "--invoke" => invoke = Some(args.next().context("--invoke needs an action")?),
+            other => anyhow::bail!(
+                "unknown argument {other:?} (usage: notify_stub [--invoke default|reply])"
+            ),
+        }
+    }
+    let _conn = zbus::blocking::connection::Builder::session()?
+        .name("org.freedesktop.Notifications")?
+        .serve_at(PATH, Stub { next_id: 0, invoke })?
+        .build()
+        .context("serve org.freedesktop.Notifications")?;
+    loop {
+        thread::park();
+    }
+}
@@ -0,0 +1,158 @@
+/// What a command does. `run_command` matches on this rather than on the
+/// name, so a command without a handler does not compile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmd {
+    Quit,
+    Down,
+    Up,
+    Top,
+    Bottom,
+    Open,
+    Insert,
+    Edit,
+    Send,
+    Add,
+    Alias,
+    Find,
+    Search,
+    Pin,
+    Archive,
+    Archived,
+    All,
+    Sort,
+    Mute,
+    Mentions,
+    NotifyTitle,
+    Verify,
+    Trust,
+    Account,
+    Timer,
+    ViewOnce,
+    Sticker,
+    Stories,
+    ReplyStory,
+    Spoilers,
+    Sync,
+    Help,
+}
+
+/// A `:` command. Keys in normal mode are shortcuts for these.
+#[derive(Debug)]
+pub struct Command {
+    pub id: Cmd,
+    pub name: &'static str,
+    // Argument synopsis for the inline help, e.g. "<+E164> <name...>".
+    pub args: &'static str,
+    pub help: &'static str,
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command { id: Cmd::Quit, name: "quit", args: "", help: "quit signal-tui" },
+    Command { id: Cmd::Down, name: "down", args: "", help: "select the next chat" },
+    Command { id: Cmd::Up, name: "up", args: "", help: "select the previous chat" },
+    Command { id: Cmd::Top, name: "top", args: "", help: "select the first chat" },
+    Command { id: Cmd::Bottom, name: "bottom", args: "", help: "select the last chat" },
+    Command { id: Cmd::Open, name: "open", args: "<chat>", help: "select the chat whose name or number matches" },
+    Command { id: Cmd::Insert, name: "insert", args: "", help: "compose a message in the selected chat" },
+    Command { id: Cmd::Edit, name: "edit", args: "", help: "compose in $VISUAL/$EDITOR" },
+    Command { id: Cmd::Send, name: "send", args: "<text...>", help: "send text (with *bold* etc. markup) to the selected chat" },
+    Command { id: Cmd::Add, name: "add", args: "[+E164]", help: "add a recipient (prompts without a number)" },
+    Command { id: Cmd::Alias, name: "alias", args: "<+E164> <name...>", help: "show a number under a name (this session)" },
+    Command { id: Cmd::Find, name: "find", args: "[text]", help: "fuzzy-find a chat by name, alias or number" },
+    Command { id: Cmd::Search, name: "search", args: "[text]", help: "highlight messages in this chat containing text (no text: clear)" },
+    Command { id: Cmd::Pin, name: "pin", args: "", help: "pin / unpin the selected chat at the top of the list" },
+    Command { id: Cmd::Archive, name: "archive", args: "", help: "archive / unarchive the selected chat (back with the next message)" },
+    Command { id: Cmd::Archived, name: "archived", args: "", help: "list the archived chats / back to the others" },
+    Command { id: Cmd::All, name: "all", args: "", help: "also list recipients without messages / hide them again" },
+    Command { id: Cmd::Sort, name: "sort", args: "[recent|unread|alpha]", help: "order of the chat list (this session)" },
+    Command { id: Cmd::Mute, name: "mute", args: "[on|off|30m|8h|1d|1w]", help: "mute notifications for the selected chat (forever or for a while)" },
+    Command { id: Cmd::Mentions, name: "mentions", args: "[on|off]", help: "notify only when a message in the selected chat mentions you" },
+    Command { id: Cmd::NotifyTitle, name: "notify-title", args: "[text]", help: "title of the selected chat's notifications (no text: default)" },
+    Command { id: Cmd::Verify, name: "verify", args: "", help: "show the selected contact's safety number" },
+    Command { id: Cmd::Trust, name: "trust", args: "", help: "mark all of the selected contact's safety numbers verified" },
+    Command { id: Cmd::Account, name: "account", args: "[n|+E164]", help: "switch account (picker without argument)" },
+    Command { id: Cmd::Timer, name: "timer", args: "[30s|5m|8h|1d|4w|off]", help: "set the disappearing-message timer" },
+    Command { id: Cmd::ViewOnce, name: "view-once", args: "", help: "open the latest view-once photo/video in the chat" },
+    Command { id: Cmd::Sticker, name: "sticker", args: "", help: "pick a sticker to send" },
+    Command { id: Cmd::Stories, name: "stories", args: "", help: "stories from the last 24h" },
+    Command { id: Cmd::ReplyStory, name: "reply-story", args: "", help: "reply to the selected story (the newest outside the story list)" },
+    Command { id: Cmd::Spoilers, name: "spoilers", args: "", help: "reveal / hide spoilers in the chat" },
+    Command { id: Cmd::Sync, name: "sync", args: "", help: "receive once now" },
+    Command { id: Cmd::Help, name: "help", args: "[command]", help: "describe a command" },
+];
+
+/// Looks a command up by name or unambiguous prefix.
+pub fn find(name: &str) -> Result<&'static Command, String> {
+    if let Some(c) = COMMANDS.iter().find(|c| c.name == name) {
+        return Ok(c);
+    }
+    let matches: Vec<&Command> = COMMANDS.iter().filter(|c| c.name.starts_with(name)).collect();
+    match matches.as_slice() {
+        [c] => Ok(c),
+        [] => Err(format!("unknown command: {name} (try :help)")),
+        _ => Err(format!(
+            "ambiguous command {name}: {}",
+            matches.iter().map(|c| c.name).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/// Splits a command line into the command name and the rest (trimmed).
+pub fn split(line: &str) -> (&str, &str) {
+    let line = line.trim().trim_start_matches(':');
+    match line.split_once(char::is_whitespace) {
+        Some((name, rest)) => (name, rest.trim()),
+        None => (line, ""),
+    }
+}
+
+/// One-line usage, e.g. ":timer [30s|5m|8h|1d|4w|off]  set the disappearing-message timer".
+pub fn usage(c: &Command) -> String {
+    if c.args.is_empty() {
+        format!(":{}  {}", c.name, c.help)
+    } else {
+        format!(":{} {}  {}", c.name, c.args, c.help)
+    }
+}
+
+/// Longest prefix shared by all candidates.
+pub fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else { return String::new(); };
+    let mut len = first.len();
+    for c in &candidates[1..] {
+        len = first
+            .char_indices()
+            .zip(c.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map(|((i, a), _)| i + a.len_utf8())
+            .unwrap_or(0)
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_ids_are_unique() {
+        for (i, c) in COMMANDS.iter().enumerate() {
+            for d in &COMMANDS[i + 1..] {
+                assert_ne!(c.name, d.name);
+                assert_ne!(c.id, d.id, "{} and {}", c.name, d.name);
+            }
+        }
+    }
+
+    #[test]
+    fn find_takes_unambiguous_prefixes() {
+        assert_eq!(find("ti").unwrap().id, Cmd::Timer);
+        assert_eq!(find("reply").unwrap().id, Cmd::ReplyStory);
+        // "archive" is also a prefix of "archived".
+        assert_eq!(find("archive").unwrap().id, Cmd::Archive);
+        assert!(find("s").unwrap_err().starts_with("ambiguous"));
+        assert!(find("nope").is_err());
+    }
+}
@@ -1,17 +1,69 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
 
+use crate::graphics::Protocol;
+use crate::hooks::{HookConfig, HookFilter};
+use crate::keys::Keymap;
+use crate::notify::{ChatRule, Mute};
+use crate::theme::Theme;
+
+/// Order of the chat list (pinned chats always come first).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatSort {
+    // Most recent message first.
+    Recent,
+    // Chats with unread messages first, then by recent activity.
+    Unread,
+    Alpha,
+}
+
+impl ChatSort {
+    pub const NAMES: [&str; 3] = ["recent", "unread", "alpha"];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim() {
+            "recent" => Some(Self::Recent),
+            "unread" => Some(Self::Unread),
+            "alpha" | "alphabetical" => Some(Self::Alpha),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Recent => "recent",
+            Self::Unread => "unread",
+            Self::Alpha => "alpha",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
+    // Drafts and other small UI state: $XDG_STATE_HOME/signal-tui.
+    pub state_dir: PathBuf,
     pub scrollback_dir: PathBuf,
     pub scrollback_load_limit: usize,
     pub save_scrollback: bool,
     pub notify: bool,
+    // Number, UUID or group id -> notification settings, from `[chats]`.
+    pub chat_rules: HashMap<String, ChatRule>,
+    // `[hooks]`; None without an on_message command.
+    pub hooks: Option<HookConfig>,
+    pub accept_view_once: bool,
+    // How sticker images are drawn; None shows stickers as text only.
+    pub graphics: Option<Protocol>,
     pub aliases: HashMap<String, String>,
+    // Shown as the sender of our own messages.
+    pub self_name: String,
+    pub sort: ChatSort,
+    pub keys: Keymap,
+    pub theme: Theme,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,7 +72,39 @@ struct ConfigFile {
     scrollback_load_limit: Option<usize>,
     save_scrollback: Option<bool>,
     notify: Option<bool>,
+    // [chats."<number or group id>"]: per-chat notification settings
+    chats: Option<HashMap<String, ChatConfig>>,
+    hooks: Option<HooksConfig>,
+    accept_view_once: Option<bool>,
+    // "auto" | "kitty" | "sixel" | "off"
+    sticker_images: Option<String>,
     aliases: Option<HashMap<String, String>>,
+    self_name: Option<String>,
+    sort: Option<String>,
+    // [keys.normal], [keys.insert], [keys.prompt], [keys.popup], [keys.finder]: key -> action
+    keys: Option<HashMap<String, HashMap<String, String>>>,
+    // name = "dark" | "light" | "high-contrast", plus per-style overrides
+    theme: Option<HashMap<String, String>>,
+    // number or UUID -> color of that sender's name
+    sender_colors: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChatConfig {
+    mute: Option<bool>,
+    mentions_only: Option<bool>,
+    title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HooksConfig {
+    on_message: Option<String>,
+    timeout_secs: Option<u64>,
+    max_running: Option<usize>,
+    // "notify" (default) or "all"
+    filter: Option<String>,
 }
 
 impl Default for ConfigFile {
@@ -30,7 +114,16 @@ impl Default for ConfigFile {
             scrollback_load_limit: Some(500),
             save_scrollback: Some(true),
             notify: Some(true),
+            chats: None,
+            hooks: None,
+            accept_view_once: Some(true),
+            sticker_images: Some("auto".to_string()),
             aliases: Some(HashMap::new()),
+            self_name: None,
+            sort: Some("alpha".to_string()),
+            keys: None,
+            theme: None,
+            sender_colors: None,
         }
     }
 }
@@ -64,15 +157,98 @@ pub fn load_or_create(config_path_override: Option<PathBuf>) -> Result<Config> {
     fs::create_dir_all(&scrollback_dir)
         .with_context(|| format!("create scrollback dir {scrollback_dir:?}"))?;
 
+    let keys = Keymap::from_config(&cf.keys.unwrap_or_default())
+        .with_context(|| format!("invalid [keys] in {config_path:?}"))?;
+
+    let sort = match cf.sort.as_deref() {
+        None => ChatSort::Alpha,
+        Some(s) => ChatSort::parse(s)
+            .with_context(|| format!("sort = {s:?} in {config_path:?}: expected recent, unread or alpha"))?,
+    };
+
+    // https://no-color.org: set and non-empty.
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let theme = Theme::from_config(
+        &cf.theme.unwrap_or_default(),
+        &cf.sender_colors.unwrap_or_default(),
+        no_color,
+    )
+    .with_context(|| format!("invalid [theme] in {config_path:?}"))?;
+
+    let chat_rules = cf
+        .chats
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(id, c)| {
+            let rule = ChatRule {
+                mute: c.mute.map(|m| if m { Mute::Forever } else { Mute::Off }),
+                mentions_only: c.mentions_only,
+                title: c.title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()),
+            };
+            (id.trim().to_string(), rule)
+        })
+        .collect();
+
+    let hooks = match cf.hooks {
+        Some(h) if h.on_message.as_deref().is_some_and(|c| !c.trim().is_empty()) => {
+            let filter = match h.filter.as_deref().map(str::trim) {
+                None | Some("notify") => HookFilter::Notify,
+                Some("all") => HookFilter::All,
+                Some(f) => bail!("[hooks] filter = {f:?} in {config_path:?}: expected notify or all"),
+            };
+            Some(HookConfig {
+                on_message: h.on_message.unwrap_or_default().trim().to_string(),
+                timeout: Duration::from_secs(h.timeout_secs.unwrap_or(10).max(1)),
+                max_running: h.max_running.unwrap_or(4).max(1),
+                filter,
+            })
+        }
+        _ => None,
+    };
+
+    let graphics = match cf.sticker_images.as_deref() {
+        None => Protocol::from_setting("auto").flatten(),
+        Some(s) => Protocol::from_setting(s).with_context(|| {
+            format!("sticker_images = {s:?} in {config_path:?}: expected auto, kitty, sixel or off")
+        })?,
+    };
+
+    let state_dir = state_dir()?;
+    fs::create_dir_all(&state_dir).with_context(|| format!("create state dir {state_dir:?}"))?;
+
     Ok(Config {
+        state_dir,
         scrollback_dir,
         scrollback_load_limit: cf.scrollback_load_limit.unwrap_or(500).clamp(50, 100_000),
         save_scrollback: cf.save_scrollback.unwrap_or(true),
         notify: cf.notify.unwrap_or(true),
+        chat_rules,
+        hooks,
+        accept_view_once: cf.accept_view_once.unwrap_or(true),
+        graphics,
         aliases: cf.aliases.unwrap_or_default(),
+        self_name: cf.self_name.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).unwrap_or_else(|| "me".to_string()),
+        sort,
+        keys,
+        theme,
     })
 }
 
+/// Just the key bindings, for `--help`: unlike `load_or_create` this writes
+/// nothing; without a config file they are the defaults.
+pub fn load_keys(config_path_override: Option<PathBuf>) -> Result<Keymap> {
+    let config_path = match config_path_override {
+        Some(p) => p,
+        None => config_path()?,
+    };
+    if !config_path.exists() {
+        return Ok(Keymap::default());
+    }
+    let raw = fs::read_to_string(&config_path).with_context(|| format!("read {config_path:?}"))?;
+    let cf: ConfigFile = toml::from_str(&raw).with_context(|| format!("parse {config_path:?}"))?;
+    Keymap::from_config(&cf.keys.unwrap_or_default()).with_context(|| format!("invalid [keys] in {config_path:?}"))
+}
+
 fn config_path() -> Result<PathBuf> {
     if let Some(p) = std::env::var_os("SIGNAL_TUI_CONFIG") {
         return Ok(PathBuf::from(p));
@@ -85,13 +261,17 @@ fn config_path() -> Result<PathBuf> {
     Ok(base.join("signal-tui").join("config.toml"))
 }
 
-fn default_scrollback_dir() -> Result<PathBuf> {
+fn state_dir() -> Result<PathBuf> {
     let base = if let Some(xdg) = std::env::var_os("XDG_STATE_HOME") {
         PathBuf::from(xdg)
     } else {
         home_dir()?.join(".local").join("state")
     };
-    Ok(base.join("signal-tui").join("scrollback"))
+    Ok(base.join("signal-tui"))
+}
+
+fn default_scrollback_dir() -> Result<PathBuf> {
+    Ok(state_dir()?.join("scrollback"))
 }
 
 fn home_dir() -> Result<PathBuf> {
@@ -118,7 +298,7 @@ fn default_config_text(default_scrollback: &Path) -> String {
 # Location of the config file:
 #   $XDG_CONFIG_HOME/signal-tui/config.toml (default: ~/.config/signal-tui/config.toml)
 #
-# Location of scrollback (saved chat history, JSONL per chat):
+# Location of scrollback (saved chat history, JSONL per chat, one subdirectory per account):
 #   $XDG_STATE_HOME/signal-tui/scrollback (default: ~/.local/state/signal-tui/scrollback)
 
 scrollback_dir = "{p}"
@@ -126,11 +306,77 @@ scrollback_load_limit = 500
 save_scrollback = true
 notify = true
 
+# View-once photos/videos can be opened exactly once, then they are deleted.
+# Set to false to refuse (delete) them as soon as they arrive.
+accept_view_once = true
+
+# Sticker images: auto (kitty graphics or sixel if the terminal is known to
+# support them), kitty, sixel or off. Stickers always show as text as well.
+sticker_images = "auto"
+
+# Name shown on your own messages.
+self_name = "me"
+
+# Chat list order: alpha (by name), recent (last message first) or unread
+# (unread chats first). Pinned chats (p) always stay on top.
+sort = "alpha"
+
 # Optional: local "address book" overrides for display names (E.164 numbers).
 #
 # [aliases]
 # "+15551234567" = "Alice"
 # "+15557654321" = "Bob"
+
+# Optional: notification settings per chat, by number or group id. m / M
+# (:mute, :mentions) change them from the TUI; those changes win over these.
+#
+# [chats."+15551234567"]
+# title = "Alice"          # notification title instead of "Signal: <chat>"
+#
+# [chats."<group id>"]
+# mentions_only = true     # only notify when the message mentions you
+# mute = true              # no notifications at all (unread still counted)
+#
+# mute is on or off here; mute for a while with :mute 8h, since a duration in
+# the config would start over with every launch.
+
+# Optional: a command run for every incoming message (sh -c), with the message
+# as JSON on stdin: kind, account, conversation, chat_name, is_group, sender,
+# sender_name, body, timestamp_ms, mentioned. Its output is discarded.
+#
+# [hooks]
+# on_message = "~/bin/signal-to-watch"
+# timeout_secs = 10        # killed after this long
+# max_running = 4          # further messages are skipped while this many run
+# filter = "notify"        # only messages that would notify (per [chats]); or "all"
+
+# Optional: key bindings, per mode (normal, insert, prompt, popup, finder). Keys are plain
+# characters or <...> names: "gg", "<C-n>", "<A-Enter>", "<Down>", "<lt>" for "<".
+# Normal-mode actions are : commands ("down", "open Alice", "timer 1d") or
+# "command-line". Insert: send, newline, cancel, edit, history-search.
+# Prompt: accept, cancel, complete, history-search. Popup: down, up, accept,
+# cancel, trust, reply. Finder: down, up, accept, cancel. "" removes a default binding.
+#
+# [keys.normal]
+# "<C-n>" = "down"
+# "<C-p>" = "up"
+# "gg" = ""
+# "<Home>" = "top"
+
+# Optional: colors. name is dark (default), light or high-contrast; the other
+# keys override single styles as "fg [on bg] [bold|italic|underlined|reversed|dim]",
+# with colors as names, 0-255 or #rrggbb. Styles: selection, unread, incoming,
+# outgoing, system, status, border, dim, highlight, warning, monospace, spoiler.
+# NO_COLOR=1 turns colors off.
+#
+# [theme]
+# name = "light"
+# selection = "white on #005f87 bold"
+
+# Optional: fixed colors for sender names (otherwise derived from the number).
+#
+# [sender_colors]
+# "+15551234567" = "lightmagenta"
 "#,
         p = default_scrollback.display()
     )
@@ -0,0 +1,265 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Text input with a cursor, emacs-style line editing and an optional
+/// multi-line mode. The cursor is a byte offset that always sits on a
+/// grapheme cluster boundary.
+#[derive(Debug, Clone, Default)]
+pub struct Editor {
+    text: String,
+    cursor: usize,
+    // Last killed text (Ctrl-w/u/k), inserted again with Ctrl-y.
+    yank: String,
+}
+
+impl Editor {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    /// Replaces the contents and puts the cursor at the end.
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = self.text.len();
+    }
+
+    pub fn lines(&self) -> Vec<&str> {
+        self.text.split('\n').collect()
+    }
+
+    /// Cursor as (line index, display column).
+    pub fn cursor_pos(&self) -> (usize, usize) {
+        let before = &self.text[..self.cursor];
+        let row = before.matches('\n').count();
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        (row, before[line_start..].width())
+    }
+
+    /// Applies an editing key. Returns false if the key was not an editing key
+    /// (e.g. Enter, Esc, or Up on the first line), so the caller can act on it.
+    /// `multiline` lets Up/Down move between lines.
+    pub fn handle_key(&mut self, k: KeyEvent, multiline: bool) -> bool {
+        let ctrl = k.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = k.modifiers.contains(KeyModifiers::ALT);
+        match k.code {
+            KeyCode::Left => self.cursor = self.prev_boundary(),
+            KeyCode::Right => self.cursor = self.next_boundary(),
+            KeyCode::Up => return multiline && self.move_line(-1),
+            KeyCode::Down => return multiline && self.move_line(1),
+            KeyCode::Home => self.cursor = self.line_start(),
+            KeyCode::End => self.cursor = self.line_end(),
+            KeyCode::Backspace if alt || ctrl => self.kill_word_back(),
+            KeyCode::Backspace => {
+                let start = self.prev_boundary();
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Delete => {
+                let end = self.next_boundary();
+                self.text.replace_range(self.cursor..end, "");
+            }
+            KeyCode::Char('a') if ctrl => self.cursor = self.line_start(),
+            KeyCode::Char('e') if ctrl => self.cursor = self.line_end(),
+            // Most terminals send Ctrl-Backspace as Ctrl-h.
+            KeyCode::Char('w' | 'h') if ctrl => self.kill_word_back(),
+            KeyCode::Char('u') if ctrl => self.kill(self.line_start(), self.cursor),
+            KeyCode::Char('k') if ctrl => self.kill(self.cursor, self.line_end()),
+            KeyCode::Char('y') if ctrl => {
+                let y = self.yank.clone();
+                self.insert_str(&y);
+            }
+            KeyCode::Char(c) if !ctrl && !alt => {
+                let mut buf = [0u8; 4];
+                self.insert_str(c.encode_utf8(&mut buf));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Inserts `s` at the cursor.
+    pub fn insert_str(&mut self, s: &str) {
+        self.text.insert_str(self.cursor, s);
+        self.cursor += s.len();
+        // A combining mark may have merged with the previous cluster.
+        self.cursor = self.snap(self.cursor);
+    }
+
+    fn kill(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.yank = self.text[start..end].to_string();
+            self.text.replace_range(start..end, "");
+            self.cursor = start;
+        }
+    }
+
+    fn kill_word_back(&mut self) {
+        let before = &self.text[..self.cursor];
+        let trimmed = before.trim_end();
+        let start = trimmed
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0);
+        self.kill(start, self.cursor);
+    }
+
+    fn move_line(&mut self, delta: isize) -> bool {
+        let lines = self.lines();
+        let (row, _) = self.cursor_pos();
+        let target = row as isize + delta;
+        if target < 0 || target as usize >= lines.len() {
+            return false;
+        }
+        let col = self.text[self.line_start()..self.cursor].graphemes(true).count();
+        let start: usize = lines[..target as usize].iter().map(|l| l.len() + 1).sum();
+        let line = lines[target as usize];
+        let offset: usize = line.graphemes(true).take(col).map(str::len).sum();
+        self.cursor = start + offset;
+        true
+    }
+
+    fn line_start(&self) -> usize {
+        self.text[..self.cursor].rfind('\n').map(|i| i + 1).unwrap_or(0)
+    }
+
+    fn line_end(&self) -> usize {
+        self.text[self.cursor..]
+            .find('\n')
+            .map(|i| self.cursor + i)
+            .unwrap_or(self.text.len())
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.cursor
+            + self.text[self.cursor..]
+                .graphemes(true)
+                .next()
+                .map(str::len)
+                .unwrap_or(0)
+    }
+
+    fn snap(&self, pos: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .map(|(i, g)| i + g.len())
+            .find(|&end| end >= pos)
+            .unwrap_or(self.text.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    fn typed(s: &str) -> Editor {
+        let mut e = Editor::default();
+        for c in s.chars() {
+            assert!(e.handle_key(key(KeyCode::Char(c)), true));
+        }
+        e
+    }
+
+    #[test]
+    fn cursor_moves_by_grapheme() {
+        // "e" + combining acute, then a family emoji made of several code points.
+        let mut e = typed("ae\u{301}👨‍👩‍👧");
+        e.handle_key(key(KeyCode::Left), false);
+        assert_eq!(e.cursor, "ae\u{301}".len());
+        e.handle_key(key(KeyCode::Left), false);
+        assert_eq!(e.cursor, 1);
+        e.handle_key(key(KeyCode::Backspace), false);
+        assert_eq!(e.text(), "e\u{301}👨‍👩‍👧");
+        e.handle_key(key(KeyCode::Delete), false);
+        assert_eq!(e.text(), "👨‍👩‍👧");
+    }
+
+    #[test]
+    fn combining_mark_joins_previous_cluster() {
+        let mut e = typed("e");
+        e.insert_str("\u{301}");
+        assert_eq!(e.cursor, e.text().len());
+        e.handle_key(key(KeyCode::Backspace), false);
+        assert_eq!(e.text(), "");
+    }
+
+    #[test]
+    fn word_kill_and_yank() {
+        let mut e = typed("hello big  world  ");
+        e.handle_key(ctrl('w'), false);
+        assert_eq!(e.text(), "hello big  ");
+        // Ctrl-Backspace, as most terminals send it.
+        e.handle_key(ctrl('h'), false);
+        assert_eq!(e.text(), "hello ");
+        e.handle_key(ctrl('a'), false);
+        e.handle_key(ctrl('y'), false);
+        assert_eq!(e.text(), "big  hello ");
+        e.handle_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::ALT), false);
+        assert_eq!(e.text(), "hello ");
+    }
+
+    #[test]
+    fn kill_to_line_ends_stays_on_line() {
+        let mut e = typed("one\ntwo three\nfour");
+        e.handle_key(key(KeyCode::Up), true);
+        e.handle_key(ctrl('k'), true);
+        assert_eq!(e.text(), "one\ntwo \nfour");
+        e.handle_key(ctrl('u'), true);
+        assert_eq!(e.text(), "one\n\nfour");
+        e.handle_key(ctrl('y'), true);
+        assert_eq!(e.text(), "one\ntwo \nfour");
+    }
+
+    #[test]
+    fn up_down_keep_column_and_stop_at_edges() {
+        let mut e = typed("abcdef\nxy");
+        assert_eq!(e.cursor_pos(), (1, 2));
+        assert!(e.handle_key(key(KeyCode::Up), true));
+        assert_eq!(e.cursor_pos(), (0, 2));
+        // No line above: left to the caller (history recall).
+        assert!(!e.handle_key(key(KeyCode::Up), true));
+        e.handle_key(key(KeyCode::End), true);
+        assert!(e.handle_key(key(KeyCode::Down), true));
+        assert_eq!(e.cursor_pos(), (1, 2));
+        // Single-line prompts never move between lines.
+        assert!(!e.handle_key(key(KeyCode::Up), false));
+    }
+
+    #[test]
+    fn non_editing_keys_are_passed_on() {
+        let mut e = typed("hi");
+        assert!(!e.handle_key(key(KeyCode::Enter), true));
+        assert!(!e.handle_key(key(KeyCode::Esc), true));
+        assert!(!e.handle_key(ctrl('r'), true));
+        assert_eq!(e.text(), "hi");
+    }
+
+    #[test]
+    fn cursor_column_counts_display_width() {
+        let e = typed("日本");
+        assert_eq!(e.cursor_pos(), (0, 4));
+    }
+}
@@ -0,0 +1,82 @@
+/// Scores `candidate` against `query` as a case-insensitive subsequence match,
+/// fzf-style: consecutive letters, word starts and a match at the very start
+/// count extra. Of all the ways the query fits, the best-scoring one counts,
+/// so a later run of consecutive letters beats scattered early ones. None if
+/// some query letter is missing. An empty query matches everything with score 0.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let cand: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let bonus = |i: usize| {
+        if i == 0 {
+            8
+        } else if !cand[i - 1].is_alphanumeric() {
+            4
+        } else {
+            0
+        }
+    };
+    // best[i]: best score of the query so far with its last letter at `i`.
+    let mut best: Option<Vec<Option<i64>>> = None;
+    for q in query.chars().flat_map(char::to_lowercase) {
+        if q.is_whitespace() {
+            continue;
+        }
+        let next: Vec<Option<i64>> = (0..cand.len())
+            .map(|i| {
+                if cand[i] != q {
+                    return None;
+                }
+                let Some(prev) = &best else { return Some(1 + bonus(i)) };
+                (0..i)
+                    .filter_map(|p| {
+                        let s = prev[p]?;
+                        // Gaps cost a little, so tighter matches win.
+                        let step = if p + 1 == i { 5 } else { -((i - p - 1).min(5) as i64) };
+                        Some(s + step)
+                    })
+                    .max()
+                    .map(|s| s + 1 + bonus(i))
+            })
+            .collect();
+        best = Some(next);
+    }
+    match best {
+        None => Some(0),
+        Some(scores) => scores.into_iter().flatten().max(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_starts_and_runs_first() {
+        let mut names = ["big engine", "general chat", "a gentle note", "gxexn"];
+        names.sort_by_key(|n| std::cmp::Reverse(score("gen", n)));
+        assert_eq!(names, ["general chat", "a gentle note", "big engine", "gxexn"]);
+    }
+
+    #[test]
+    fn takes_the_best_alignment() {
+        // A first-occurrence match would take the scattered "c h a" and lose
+        // to "c h a t"; the word "chat" later on is the better fit.
+        assert!(score("chat", "c h a chat") > score("chat", "c h a t"));
+        assert_eq!(score("chat", "c h a chat"), Some(23));
+    }
+
+    #[test]
+    fn ignores_case_and_query_spaces() {
+        assert_eq!(score("ALICE", "alice"), score("alice", "Alice"));
+        assert_eq!(score("bob s", "Bob Smith"), score("bobs", "bob smith"));
+    }
+
+    #[test]
+    fn missing_letters_do_not_match() {
+        assert_eq!(score("xyz", "alice"), None);
+        // Every letter is there, but not in order.
+        assert_eq!(score("ba", "ab"), None);
+        assert_eq!(score("a", ""), None);
+        assert_eq!(score("", "anything"), Some(0));
+        assert_eq!(score("  ", "anything"), Some(0));
+    }
+}
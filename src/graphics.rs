@@ -0,0 +1,276 @@
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::{Context, Result};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use image::RgbaImage;
+use image::imageops::{self, FilterType};
+
+/// Terminal rows a sticker image takes up below its `[sticker: ...]` line.
+pub const STICKER_ROWS: u16 = 6;
+
+/// How the terminal can show images, if at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    // https://sw.kovidgoyal.net/kitty/graphics-protocol/ (kitty, WezTerm, Ghostty)
+    Kitty,
+    Sixel,
+}
+
+impl Protocol {
+    /// `sticker_images` from the config: auto, kitty, sixel or off.
+    pub fn from_setting(s: &str) -> Option<Option<Self>> {
+        match s.trim() {
+            "auto" => Some(detect()),
+            "kitty" => Some(Some(Self::Kitty)),
+            "sixel" => Some(Some(Self::Sixel)),
+            "off" => Some(None),
+            _ => None,
+        }
+    }
+}
+
+/// Guesses the graphics protocol from the environment; terminals that cannot
+/// be recognized this way get text only (set `sticker_images` to force one).
+pub fn detect() -> Option<Protocol> {
+    let var = |k: &str| std::env::var(k).unwrap_or_default();
+    // Inside tmux/screen the variables describe the outer terminal, but
+    // the escapes would not get through.
+    if !var("TMUX").is_empty() || var("TERM").starts_with("screen") {
+        return None;
+    }
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+    if !var("KITTY_WINDOW_ID").is_empty()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || matches!(program.as_str(), "WezTerm" | "ghostty")
+    {
+        return Some(Protocol::Kitty);
+    }
+    if term.starts_with("foot")
+        || term.contains("mlterm")
+        || term.contains("sixel")
+        || matches!(program.as_str(), "iTerm.app" | "contour")
+    {
+        return Some(Protocol::Sixel);
+    }
+    None
+}
+
+/// Pixel size of one terminal cell; terminals that do not report it get a
+/// common 10x20.
+pub fn cell_size() -> (u32, u32) {
+    match crossterm::terminal::window_size() {
+        Ok(w) if w.width > 0 && w.height > 0 && w.columns > 0 && w.rows > 0 => (
+            u32::from(w.width / w.columns).max(1),
+            u32::from(w.height / w.rows).max(1),
+        ),
+        _ => (10, 20),
+    }
+}
+
+/// An image encoded for the terminal, `cols` x `rows` cells in size.
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub cols: u16,
+    pub rows: u16,
+    // Kitty: sent once, then placed by id. Sixel: the pixels themselves, every time.
+    transmit: Option<String>,
+    place: String,
+}
+
+impl Image {
+    /// Decodes `bytes` (PNG or WebP; animated stickers show their first
+    /// frame) and scales it into a box `rows` cells high.
+    pub fn decode(protocol: Protocol, bytes: &[u8], rows: u16, cell: (u32, u32)) -> Result<Self> {
+        let img = image::load_from_memory(bytes).context("decode sticker image")?.to_rgba8();
+        let (cw, ch) = cell;
+        let box_px = u32::from(rows) * ch;
+        let img = fit(&img, box_px, box_px);
+        let cols = img.width().div_ceil(cw).max(1) as u16;
+        let rows = img.height().div_ceil(ch).max(1) as u16;
+        Ok(match protocol {
+            Protocol::Kitty => {
+                let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+                Self {
+                    cols,
+                    rows,
+                    transmit: Some(kitty_transmit(id, &img)),
+                    place: format!("\x1b_Ga=p,i={id},c={cols},r={rows},C=1,q=2\x1b\\"),
+                }
+            }
+            Protocol::Sixel => Self { cols, rows, transmit: None, place: sixel(&img) },
+        })
+    }
+
+    /// Escapes that upload the image; only needed once (kitty keeps it).
+    pub fn transmit(&self) -> Option<&str> {
+        self.transmit.as_deref()
+    }
+
+    /// Escapes that draw the image at the cursor.
+    pub fn place(&self) -> &str {
+        &self.place
+    }
+}
+
+/// Removes every kitty image placement (uploaded images are kept).
+pub const KITTY_CLEAR: &str = "\x1b_Ga=d,d=a,q=2\x1b\\";
+
+/// Removes every kitty image and its placements, for when we exit.
+pub const KITTY_DELETE_ALL: &str = "\x1b_Ga=d,d=A,q=2\x1b\\";
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Scales `img` down (never up) to fit `max_w` x `max_h`, keeping its aspect.
+fn fit(img: &RgbaImage, max_w: u32, max_h: u32) -> RgbaImage {
+    let (w, h) = img.dimensions();
+    if w <= max_w && h <= max_h {
+        return img.clone();
+    }
+    let scale = f64::min(f64::from(max_w) / f64::from(w), f64::from(max_h) / f64::from(h));
+    let nw = ((f64::from(w) * scale) as u32).max(1);
+    let nh = ((f64::from(h) * scale) as u32).max(1);
+    imageops::resize(img, nw, nh, FilterType::Triangle)
+}
+
+fn kitty_transmit(id: u32, img: &RgbaImage) -> String {
+    // The protocol caps each escape's payload at 4096 bytes of base64.
+    let data = BASE64.encode(img.as_raw());
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            let (w, h) = img.dimensions();
+            let _ = write!(out, "\x1b_Ga=t,f=32,i={id},s={w},v={h},q=2,m={more};{chunk}\x1b\\");
+        } else {
+            let _ = write!(out, "\x1b_Gm={more};{chunk}\x1b\\");
+        }
+    }
+    out
+}
+
+/// Sixel with a fixed 6x6x6 color cube; transparent pixels are left unpainted.
+fn sixel(img: &RgbaImage) -> String {
+    let (w, h) = img.dimensions();
+    let level = |c: u8| (u16::from(c) * 5 + 127) / 255;
+    let index = |x: u32, y: u32| -> Option<u16> {
+        let p = img.get_pixel(x, y).0;
+        (p[3] >= 128).then(|| level(p[0]) * 36 + level(p[1]) * 6 + level(p[2]))
+    };
+
+    // P2=1: pixels we do not set keep the background.
+    let mut out = format!("\x1bP0;1;0q\"1;1;{w};{h}");
+    for i in 0..216u16 {
+        let pct = |v: u16| v * 100 / 5;
+        let _ = write!(out, "#{i};2;{};{};{}", pct(i / 36), pct(i / 6 % 6), pct(i % 6));
+    }
+    for top in (0..h).step_by(6) {
+        let band = top..(top + 6).min(h);
+        let mut colors: Vec<u16> = Vec::new();
+        for y in band.clone() {
+            for x in 0..w {
+                if let Some(c) = index(x, y)
+                    && !colors.contains(&c)
+                {
+                    colors.push(c);
+                }
+            }
+        }
+        for c in colors {
+            let _ = write!(out, "#{c}");
+            let mut run: Option<(u8, u32)> = None;
+            for x in 0..w {
+                let bits = band
+                    .clone()
+                    .filter(|&y| index(x, y) == Some(c))
+                    .fold(0u8, |b, y| b | 1 << (y - top));
+                run = match run {
+                    Some((b, n)) if b == bits => Some((b, n + 1)),
+                    Some(prev) => {
+                        push_run(&mut out, prev);
+                        Some((bits, 1))
+                    }
+                    None => Some((bits, 1)),
+                };
+            }
+            if let Some(prev) = run {
+                push_run(&mut out, prev);
+            }
+            // Back to the start of the band for the next color.
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+fn push_run(out: &mut String, (bits, n): (u8, u32)) {
+    let ch = char::from(63 + bits);
+    if n > 3 {
+        let _ = write!(out, "!{n}{ch}");
+    } else {
+        for _ in 0..n {
+            out.push(ch);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn fit_keeps_aspect_and_never_upscales() {
+        let img = RgbaImage::new(200, 100);
+        assert_eq!(fit(&img, 50, 50).dimensions(), (50, 25));
+        let small = RgbaImage::new(10, 20);
+        assert_eq!(fit(&small, 50, 50).dimensions(), (10, 20));
+    }
+
+    #[test]
+    fn kitty_splits_payload_into_chunks() {
+        // 40x40 RGBA = 6400 bytes -> 8536 base64 bytes -> 3 chunks.
+        let img = RgbaImage::new(40, 40);
+        let out = kitty_transmit(7, &img);
+        assert!(out.starts_with("\x1b_Ga=t,f=32,i=7,s=40,v=40,q=2,m=1;"));
+        assert_eq!(out.matches("\x1b_G").count(), 3);
+        assert!(out.contains("\x1b_Gm=1;"));
+        assert!(out.contains("\x1b_Gm=0;"));
+    }
+
+    #[test]
+    fn sixel_paints_opaque_pixels_only() {
+        let mut img = RgbaImage::new(5, 2);
+        for x in 0..5 {
+            img.put_pixel(x, 0, Rgba([255, 0, 0, 255]));
+        }
+        let out = sixel(&img);
+        assert!(out.starts_with("\x1bP0;1;0q\"1;1;5;2"));
+        assert!(out.ends_with("-\x1b\\"));
+        // Red is cube index 5*36 = 180; one band, top row set in all 5 columns.
+        assert!(out.contains("#180!5@$-"));
+        // The transparent second row is never drawn.
+        assert!(!out.contains("#0!"));
+    }
+
+    #[test]
+    fn decode_sizes_in_cells() {
+        let mut png = Vec::new();
+        let img = image::DynamicImage::ImageRgba8(RgbaImage::new(512, 512));
+        img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+        let out = Image::decode(Protocol::Sixel, &png, 6, (10, 20)).unwrap();
+        // 6 rows of 20px = 120px square -> 12 columns of 10px.
+        assert_eq!((out.cols, out.rows), (12, 6));
+        assert!(out.transmit().is_none());
+        let out = Image::decode(Protocol::Kitty, &png, 6, (10, 20)).unwrap();
+        assert!(out.transmit().is_some());
+        assert!(out.place().starts_with("\x1b_Ga=p,"));
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::state;
+
+// Per list; the oldest entries are dropped first.
+const MAX_COMMANDS: usize = 500;
+const MAX_MESSAGES: usize = 100;
+
+/// Which history a prompt recalls from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryList {
+    // Prompt input (recipients, timers, ...), shared by everything.
+    Commands,
+    // Sent messages of one conversation: (account, conversation key).
+    Messages(String, String),
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryFile {
+    #[serde(default)]
+    commands: Vec<String>,
+    // account -> conversation key -> sent messages, oldest first
+    #[serde(default)]
+    messages: HashMap<String, HashMap<String, Vec<String>>>,
+}
+
+/// Input history, persisted to `history.json` in the state dir.
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+    data: HistoryFile,
+    // Sent messages go to disk too; off with `save_scrollback = false`, so
+    // they are only kept for the session.
+    save_messages: bool,
+}
+
+impl History {
+    pub fn load(state_dir: &Path, save_messages: bool) -> Self {
+        let path = state_dir.join("history.json");
+        // A missing or broken file just means an empty history.
+        let data = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            data,
+            save_messages,
+        }
+    }
+
+    /// Entries of `list`, oldest first.
+    pub fn entries(&self, list: &HistoryList) -> &[String] {
+        match list {
+            HistoryList::Commands => &self.data.commands,
+            HistoryList::Messages(account, key) => self
+                .data
+                .messages
+                .get(account)
+                .and_then(|m| m.get(key))
+                .map(Vec::as_slice)
+                .unwrap_or(&[]),
+        }
+    }
+
+    /// Appends `entry` (skipping blanks and repeats of the last entry) and saves.
+    pub fn push(&mut self, list: &HistoryList, entry: &str) -> Result<()> {
+        if entry.trim().is_empty() {
+            return Ok(());
+        }
+        let (v, max) = match list {
+            HistoryList::Commands => (&mut self.data.commands, MAX_COMMANDS),
+            HistoryList::Messages(account, key) => (
+                self.data
+                    .messages
+                    .entry(account.clone())
+                    .or_default()
+                    .entry(key.clone())
+                    .or_default(),
+                MAX_MESSAGES,
+            ),
+        };
+        if v.last().map(String::as_str) == Some(entry) {
+            return Ok(());
+        }
+        v.push(entry.to_string());
+        if v.len() > max {
+            v.drain(..v.len() - max);
+        }
+        self.save()
+    }
+
+    /// Drops the sent messages of a conversation, e.g. once it has a
+    /// disappearing-message timer.
+    pub fn forget(&mut self, account: &str, conversation_key: &str) -> Result<()> {
+        let Some(chats) = self.data.messages.get_mut(account) else {
+            return Ok(());
+        };
+        if chats.remove(conversation_key).is_none() {
+            return Ok(());
+        }
+        if chats.is_empty() {
+            self.data.messages.remove(account);
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let json = if self.save_messages {
+            serde_json::to_string(&self.data)
+        } else {
+            serde_json::to_string(&HistoryFile {
+                commands: self.data.commands.clone(),
+                messages: HashMap::new(),
+            })
+        }
+        .context("serialize history")?;
+        state::write_private(&self.path, json.as_bytes())
+    }
+
+    /// Index of the newest entry before `before` that contains `query`.
+    pub fn search(&self, list: &HistoryList, query: &str, before: usize) -> Option<usize> {
+        let entries = self.entries(list);
+        entries[..before.min(entries.len())]
+            .iter()
+            .rposition(|e| e.contains(query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("signal-tui-history-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn chat(key: &str) -> HistoryList {
+        HistoryList::Messages("+15550000001".to_string(), key.to_string())
+    }
+
+    #[test]
+    fn push_skips_blanks_and_repeats() {
+        let dir = temp_dir("push");
+        let mut h = History::load(&dir, true);
+        for e in ["one", "  ", "two", "two", "one"] {
+            h.push(&HistoryList::Commands, e).unwrap();
+        }
+        assert_eq!(h.entries(&HistoryList::Commands), ["one", "two", "one"]);
+        assert!(h.entries(&chat("contact:+1")).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn push_drops_the_oldest_entries() {
+        let dir = temp_dir("limit");
+        let mut h = History::load(&dir, true);
+        for i in 0..MAX_MESSAGES + 3 {
+            h.push(&chat("contact:+1"), &format!("m{i}")).unwrap();
+        }
+        let entries = h.entries(&chat("contact:+1"));
+        assert_eq!(entries.len(), MAX_MESSAGES);
+        assert_eq!(entries[0], "m3");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn search_finds_the_newest_match_before_an_index() {
+        let dir = temp_dir("search");
+        let mut h = History::load(&dir, true);
+        for e in ["open alice", "timer 1d", "open bob", "quit"] {
+            h.push(&HistoryList::Commands, e).unwrap();
+        }
+        let list = HistoryList::Commands;
+        assert_eq!(h.search(&list, "open", 4), Some(2));
+        assert_eq!(h.search(&list, "open", 2), Some(0));
+        assert_eq!(h.search(&list, "open", 0), None);
+        assert_eq!(h.search(&list, "open", 99), Some(2));
+        assert_eq!(h.search(&list, "nope", 4), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn messages_stay_off_disk_without_scrollback() {
+        let dir = temp_dir("private");
+        let mut h = History::load(&dir, false);
+        h.push(&HistoryList::Commands, "timer 1d").unwrap();
+        h.push(&chat("contact:+1"), "secret").unwrap();
+        // Still recalled in this session.
+        assert_eq!(h.entries(&chat("contact:+1")), ["secret"]);
+        let raw = fs::read_to_string(dir.join("history.json")).unwrap();
+        assert!(raw.contains("timer 1d") && !raw.contains("secret"), "{raw}");
+        let h = History::load(&dir, true);
+        assert!(h.entries(&chat("contact:+1")).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn forget_removes_a_chat_from_disk() {
+        let dir = temp_dir("forget");
+        let mut h = History::load(&dir, true);
+        h.push(&chat("contact:+1"), "gone soon").unwrap();
+        h.push(&chat("contact:+2"), "kept").unwrap();
+        h.forget("+15550000001", "contact:+1").unwrap();
+        let h = History::load(&dir, true);
+        assert!(h.entries(&chat("contact:+1")).is_empty());
+        assert_eq!(h.entries(&chat("contact:+2")), ["kept"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
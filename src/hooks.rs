@@ -0,0 +1,202 @@
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+/// Which messages run the hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookFilter {
+    All,
+    // Those that would notify: muted chats are skipped, mentions-only chats
+    // only run it for mentions (the `[chats]` / `:mute` / `:mentions` rules).
+    Notify,
+}
+
+/// `[hooks]` from the config file.
+#[derive(Debug, Clone)]
+pub struct HookConfig {
+    // Run with `sh -c`, the event as JSON on stdin.
+    pub on_message: String,
+    pub timeout: Duration,
+    // Events arriving while this many hooks still run are dropped.
+    pub max_running: usize,
+    pub filter: HookFilter,
+}
+
+/// What `on_message` gets on stdin, one JSON object.
+#[derive(Debug, Serialize)]
+pub struct MessageEvent<'a> {
+    // "message", or "call" for a missed call.
+    pub kind: &'a str,
+    pub account: &'a str,
+    // Conversation key: "contact:+15551234567" or "group:<id>".
+    pub conversation: &'a str,
+    pub chat_name: &'a str,
+    pub is_group: bool,
+    pub sender: Option<&'a str>,
+    pub sender_name: Option<&'a str>,
+    pub body: &'a str,
+    pub timestamp_ms: Option<i64>,
+    pub mentioned: bool,
+}
+
+pub struct Hooks {
+    cfg: HookConfig,
+    running: Arc<AtomicUsize>,
+}
+
+impl Hooks {
+    pub fn new(cfg: HookConfig) -> Self {
+        Self { cfg, running: Arc::new(AtomicUsize::new(0)) }
+    }
+
+    pub fn filter(&self) -> HookFilter {
+        self.cfg.filter
+    }
+
+    /// Starts `on_message` for `ev` and returns; background threads feed it
+    /// stdin and kill it (with its children) once the timeout passes, counted
+    /// from the start.
+    pub fn on_message(&self, ev: &MessageEvent) -> Result<()> {
+        let running = self.running.load(Ordering::Relaxed);
+        if running >= self.cfg.max_running {
+            bail!("on_message hook skipped: {running} still running");
+        }
+        let json = serde_json::to_vec(ev).context("serialize hook event")?;
+        // Its output would land on top of the TUI.
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.cfg.on_message)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .spawn()
+            .with_context(|| format!("run on_message hook {:?}", self.cfg.on_message))?;
+
+        let guard = Running::start(&self.running);
+        let deadline = Instant::now() + self.cfg.timeout;
+        if let Some(mut stdin) = child.stdin.take() {
+            // On its own thread, so a hook that never reads stdin cannot hold
+            // off the deadline; a hook that exits first just breaks the pipe.
+            thread::spawn(move || {
+                let _ = stdin.write_all(&json);
+            });
+        }
+        thread::spawn(move || {
+            let _guard = guard;
+            while matches!(child.try_wait(), Ok(None)) {
+                if Instant::now() >= deadline {
+                    // The whole process group, so `sh -c "a | b"` goes too.
+                    // SAFETY: a plain syscall; the group is the one `process_group(0)`
+                    // made for the child, which is not reaped yet.
+                    unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) };
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Counts a hook as running until dropped.
+struct Running(Arc<AtomicUsize>);
+
+impl Running {
+    fn start(running: &Arc<AtomicUsize>) -> Self {
+        running.fetch_add(1, Ordering::Relaxed);
+        Self(running.clone())
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hooks(on_message: &str, timeout_ms: u64, max_running: usize) -> Hooks {
+        Hooks::new(HookConfig {
+            on_message: on_message.to_string(),
+            timeout: Duration::from_millis(timeout_ms),
+            max_running,
+            filter: HookFilter::All,
+        })
+    }
+
+    fn event() -> MessageEvent<'static> {
+        MessageEvent {
+            kind: "message",
+            account: "+15550000001",
+            conversation: "contact:+15550000002",
+            chat_name: "Alice",
+            is_group: false,
+            sender: Some("+15550000002"),
+            sender_name: Some("Alice"),
+            body: "hi",
+            timestamp_ms: Some(1),
+            mentioned: false,
+        }
+    }
+
+    fn wait_until(what: &str, mut done: impl FnMut() -> bool) {
+        let start = Instant::now();
+        while !done() {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out waiting for {what}");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    // Running, not gone or a zombie waiting for init to reap it.
+    fn alive(pid: u32) -> bool {
+        std::fs::read_to_string(format!("/proc/{pid}/stat"))
+            .is_ok_and(|stat| stat.rsplit(") ").next().is_some_and(|rest| !rest.starts_with('Z')))
+    }
+
+    #[test]
+    fn timeout_kills_the_hook_group_and_frees_its_slot() {
+        let pid_file = std::env::temp_dir().join(format!("signal-tui-hook-{}", std::process::id()));
+        let _ = std::fs::remove_file(&pid_file);
+        // The sleep is a grandchild: only killing the process group gets it.
+        let h = hooks(&format!("sleep 10 & echo $! > {}; wait", pid_file.display()), 300, 1);
+        h.on_message(&event()).unwrap();
+        assert_eq!(h.running.load(Ordering::Relaxed), 1);
+
+        let mut pid = 0;
+        wait_until("the pid file", || {
+            pid = std::fs::read_to_string(&pid_file).ok().and_then(|s| s.trim().parse().ok()).unwrap_or(0);
+            pid != 0
+        });
+        assert!(alive(pid));
+        let start = Instant::now();
+        wait_until("the slot", || h.running.load(Ordering::Relaxed) == 0);
+        assert!(start.elapsed() < Duration::from_secs(2), "not killed at the timeout");
+        wait_until("the sleep to die", || !alive(pid));
+        std::fs::remove_file(pid_file).unwrap();
+    }
+
+    #[test]
+    fn hooks_over_the_limit_are_skipped() {
+        let h = hooks("sleep 10", 300, 1);
+        h.on_message(&event()).unwrap();
+        let err = h.on_message(&event()).unwrap_err();
+        assert!(format!("{err:#}").contains("skipped: 1 still running"), "{err:#}");
+        // Room again once the first one is killed.
+        wait_until("the slot", || h.running.load(Ordering::Relaxed) == 0);
+        h.on_message(&event()).unwrap();
+        wait_until("the slot", || h.running.load(Ordering::Relaxed) == 0);
+    }
+}
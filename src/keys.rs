@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::commands;
+
+/// Which set of bindings applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyMode {
+    Normal,
+    Insert,
+    // One-line prompts: add-recipient, timer and the `:` command line.
+    Prompt,
+    // List popups: accounts, stickers, stories and the safety numbers.
+    Popup,
+    // The fuzzy finder, which also takes typed text.
+    Finder,
+}
+
+impl KeyMode {
+    const ALL: [(KeyMode, &'static str); 5] = [
+        (KeyMode::Normal, "normal"),
+        (KeyMode::Insert, "insert"),
+        (KeyMode::Prompt, "prompt"),
+        (KeyMode::Popup, "popup"),
+        (KeyMode::Finder, "finder"),
+    ];
+
+    // Actions other than `:` commands, per mode.
+    fn actions(self) -> &'static [&'static str] {
+        match self {
+            KeyMode::Normal => &["command-line"],
+            KeyMode::Insert => &["send", "newline", "cancel", "edit", "history-search"],
+            KeyMode::Prompt => &["accept", "cancel", "complete", "history-search"],
+            KeyMode::Popup => &["down", "up", "accept", "cancel", "trust", "reply"],
+            KeyMode::Finder => &["down", "up", "accept", "cancel"],
+        }
+    }
+
+    // Popups and the finder look at one key at a time.
+    fn single_keys(self) -> bool {
+        matches!(self, KeyMode::Popup | KeyMode::Finder)
+    }
+}
+
+// Built-in bindings; `[keys.<mode>]` entries override or remove them.
+const DEFAULTS: &[(KeyMode, &str, &str)] = &[
+    (KeyMode::Normal, "j", "down"),
+    (KeyMode::Normal, "<Down>", "down"),
+    (KeyMode::Normal, "k", "up"),
+    (KeyMode::Normal, "<Up>", "up"),
+    (KeyMode::Normal, "gg", "top"),
+    (KeyMode::Normal, "G", "bottom"),
+    (KeyMode::Normal, "i", "insert"),
+    (KeyMode::Normal, "E", "edit"),
+    (KeyMode::Normal, "a", "add"),
+    (KeyMode::Normal, "v", "verify"),
+    (KeyMode::Normal, "A", "account"),
+    (KeyMode::Normal, "T", "timer"),
+    (KeyMode::Normal, "o", "view-once"),
+    (KeyMode::Normal, "S", "sticker"),
+    (KeyMode::Normal, "s", "stories"),
+    (KeyMode::Normal, "z", "spoilers"),
+    (KeyMode::Normal, "<C-p>", "find"),
+    (KeyMode::Normal, "/", "find"),
+    (KeyMode::Normal, "p", "pin"),
+    (KeyMode::Normal, "x", "archive"),
+    (KeyMode::Normal, "X", "archived"),
+    (KeyMode::Normal, "H", "all"),
+    (KeyMode::Normal, "m", "mute"),
+    (KeyMode::Normal, "M", "mentions"),
+    (KeyMode::Normal, "r", "sync"),
+    (KeyMode::Normal, ":", "command-line"),
+    (KeyMode::Normal, "q", "quit"),
+    (KeyMode::Insert, "<Enter>", "send"),
+    (KeyMode::Insert, "<A-Enter>", "newline"),
+    (KeyMode::Insert, "<S-Enter>", "newline"),
+    (KeyMode::Insert, "<C-j>", "newline"),
+    (KeyMode::Insert, "<Esc>", "cancel"),
+    (KeyMode::Insert, "<C-x>", "edit"),
+    (KeyMode::Insert, "<C-r>", "history-search"),
+    (KeyMode::Prompt, "<Enter>", "accept"),
+    (KeyMode::Prompt, "<Esc>", "cancel"),
+    (KeyMode::Prompt, "<Tab>", "complete"),
+    (KeyMode::Prompt, "<C-r>", "history-search"),
+    (KeyMode::Popup, "j", "down"),
+    (KeyMode::Popup, "<Down>", "down"),
+    (KeyMode::Popup, "k", "up"),
+    (KeyMode::Popup, "<Up>", "up"),
+    (KeyMode::Popup, "<Enter>", "accept"),
+    (KeyMode::Popup, "<Esc>", "cancel"),
+    (KeyMode::Popup, "q", "cancel"),
+    (KeyMode::Popup, "t", "trust"),
+    (KeyMode::Popup, "r", "reply"),
+    (KeyMode::Finder, "<Down>", "down"),
+    (KeyMode::Finder, "<C-n>", "down"),
+    (KeyMode::Finder, "<Up>", "up"),
+    (KeyMode::Finder, "<C-p>", "up"),
+    (KeyMode::Finder, "<Enter>", "accept"),
+    (KeyMode::Finder, "<Esc>", "cancel"),
+];
+
+/// One key press, normalized so that e.g. `G` and Shift+g compare equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Chord {
+    code: KeyCode,
+    mods: KeyModifiers,
+}
+
+impl Chord {
+    fn new(code: KeyCode, mods: KeyModifiers) -> Self {
+        let mut mods = mods & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        // Shift is already part of the character ('G', ':').
+        if let KeyCode::Char(_) = code {
+            mods.remove(KeyModifiers::SHIFT);
+        }
+        Self { code, mods }
+    }
+
+    fn from_event(k: &KeyEvent) -> Self {
+        Self::new(k.code, k.modifiers)
+    }
+
+    fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("F{n}"),
+            code => NAMED
+                .iter()
+                .find(|(_, c)| *c == code)
+                .map(|(n, _)| n.to_string())
+                .unwrap_or_else(|| format!("{code:?}")),
+        };
+        let mut out = String::new();
+        for (m, name) in [(KeyModifiers::CONTROL, "Ctrl-"), (KeyModifiers::ALT, "Alt-"), (KeyModifiers::SHIFT, "Shift-")] {
+            if self.mods.contains(m) {
+                out.push_str(name);
+            }
+        }
+        out + &key
+    }
+}
+
+const NAMED: &[(&str, KeyCode)] = &[
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Tab", KeyCode::Tab),
+    ("BackTab", KeyCode::BackTab),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+];
+
+/// Parses a key sequence: plain characters, or `<...>` for named keys and
+/// modifiers, e.g. `gg`, `<C-p>`, `<A-Enter>`, `<Down>`, `<lt>` for `<`.
+fn parse_sequence(spec: &str) -> Result<Vec<Chord>> {
+    let mut out = Vec::new();
+    let mut rest = spec;
+    while let Some(c) = rest.chars().next() {
+        if c == '<'
+            && let Some(end) = rest.find('>')
+        {
+            out.push(parse_bracketed(&rest[1..end]).with_context(|| format!("bad key {spec:?}"))?);
+            rest = &rest[end + 1..];
+        } else {
+            out.push(Chord::new(KeyCode::Char(c), KeyModifiers::NONE));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if out.is_empty() {
+        bail!("empty key");
+    }
+    Ok(out)
+}
+
+fn parse_bracketed(inner: &str) -> Result<Chord> {
+    let mut mods = KeyModifiers::NONE;
+    let mut name = inner;
+    // Modifier prefixes, but a lone "-" is the minus key.
+    while name.len() > 2 && name.as_bytes()[1] == b'-' {
+        mods |= match name.as_bytes()[0].to_ascii_uppercase() {
+            b'C' => KeyModifiers::CONTROL,
+            b'A' | b'M' => KeyModifiers::ALT,
+            b'S' => KeyModifiers::SHIFT,
+            _ => bail!("unknown modifier in <{inner}>"),
+        };
+        name = &name[2..];
+    }
+    let code = match name.to_ascii_lowercase().as_str() {
+        "cr" | "return" => KeyCode::Enter,
+        "bs" => KeyCode::Backspace,
+        "del" => KeyCode::Delete,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        lower => {
+            let mut chars = name.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                // Terminals report Ctrl/Alt letters in lower case.
+                if mods.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+                    KeyCode::Char(c.to_ascii_lowercase())
+                } else {
+                    KeyCode::Char(c)
+                }
+            } else if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                KeyCode::F(n)
+            } else {
+                NAMED
+                    .iter()
+                    .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                    .map(|(_, c)| *c)
+                    .with_context(|| format!("unknown key <{inner}>"))?
+            }
+        }
+    };
+    Ok(Chord::new(code, mods))
+}
+
+/// Result of feeding one key press to the keymap.
+#[derive(Debug, Default)]
+pub struct Fed {
+    // Keys that turned out not to be bound, in order; handle them as usual.
+    pub unbound: Vec<KeyEvent>,
+    pub action: Option<String>,
+}
+
+/// Key bindings for every mode, built from the defaults and `[keys]`.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    // Per mode, in definition order (which is also the help order).
+    bindings: HashMap<KeyMode, Vec<(Vec<Chord>, String)>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_config(&HashMap::new()).expect("default key bindings are valid")
+    }
+}
+
+impl Keymap {
+    /// Applies `[keys.<mode>]` tables (`"<key>" = "<action>"`, `""` unbinds) on
+    /// top of the defaults and rejects unknown actions and conflicting keys.
+    pub fn from_config(raw: &HashMap<String, HashMap<String, String>>) -> Result<Self> {
+        let mut bindings: HashMap<KeyMode, Vec<(Vec<Chord>, String)>> = HashMap::new();
+        for &(mode, key, action) in DEFAULTS {
+            let seq = parse_sequence(key)?;
+            bindings.entry(mode).or_default().push((seq, action.to_string()));
+        }
+
+        for (mode_name, table) in raw {
+            let Some(&(mode, _)) = KeyMode::ALL.iter().find(|(_, n)| n == mode_name) else {
+                let names: Vec<&str> = KeyMode::ALL.iter().map(|(_, n)| *n).collect();
+                bail!("unknown key mode [keys.{mode_name}] (expected one of: {})", names.join(", "));
+            };
+            let list = bindings.entry(mode).or_default();
+            // Sorted so errors do not depend on hash order.
+            let mut entries: Vec<_> = table.iter().collect();
+            entries.sort();
+            let mut seen: Vec<(Vec<Chord>, &str)> = Vec::new();
+            for (key, action) in entries {
+                let seq = parse_sequence(key).with_context(|| format!("[keys.{mode_name}]"))?;
+                if mode.single_keys() && seq.len() > 1 {
+                    bail!("[keys.{mode_name}]: {key:?} is a sequence; this mode takes single keys");
+                }
+                if let Some((_, other)) = seen.iter().find(|(s, _)| *s == seq) {
+                    bail!("[keys.{mode_name}]: {key:?} and {other:?} are the same key");
+                }
+                seen.push((seq.clone(), key));
+                list.retain(|(s, _)| *s != seq);
+                let action = action.trim();
+                if action.is_empty() || action == "none" {
+                    continue;
+                }
+                validate_action(mode, action).with_context(|| format!("[keys.{mode_name}] {key:?}"))?;
+                list.push((seq, action.to_string()));
+            }
+        }
+
+        // A key that starts a longer sequence would never let it fire.
+        for (mode, name) in KeyMode::ALL {
+            let list = bindings.get(&mode).map(Vec::as_slice).unwrap_or(&[]);
+            for (a, action_a) in list {
+                for (b, action_b) in list {
+                    if a.len() < b.len() && b.starts_with(a) {
+                        bail!(
+                            "[keys.{name}]: {} ({action_a}) hides {} ({action_b}); unbind one of them",
+                            seq_label(a),
+                            seq_label(b)
+                        );
+                    }
+                }
+            }
+        }
+        Ok(Self { bindings })
+    }
+
+    /// Adds `k` to the pending sequence and resolves it. A sequence that breaks
+    /// off returns its earlier keys as unbound and starts over with `k`.
+    pub fn feed(&self, mode: KeyMode, pending: &mut Vec<KeyEvent>, k: KeyEvent) -> Fed {
+        pending.push(k);
+        let seq: Vec<Chord> = pending.iter().map(Chord::from_event).collect();
+        let list = self.bindings.get(&mode).map(Vec::as_slice).unwrap_or(&[]);
+        if let Some((_, action)) = list.iter().find(|(s, _)| *s == seq) {
+            pending.clear();
+            return Fed { unbound: Vec::new(), action: Some(action.clone()) };
+        }
+        if list.iter().any(|(s, _)| s.starts_with(&seq)) {
+            return Fed::default();
+        }
+        let mut keys = std::mem::take(pending);
+        if keys.len() == 1 {
+            return Fed { unbound: keys, action: None };
+        }
+        let last = keys.pop().expect("len > 1");
+        let mut fed = self.feed(mode, pending, last);
+        keys.append(&mut fed.unbound);
+        fed.unbound = keys;
+        fed
+    }
+
+    /// Labels of the keys bound to `action`, e.g. ["j", "Down"].
+    pub fn keys_for(&self, mode: KeyMode, action: &str) -> Vec<String> {
+        self.bindings
+            .get(&mode)
+            .into_iter()
+            .flatten()
+            .filter(|(_, a)| a == action)
+            .map(|(s, _)| seq_label(s))
+            .collect()
+    }
+
+    /// Whether `k` on its own is bound to `action`; for popups and the finder,
+    /// which take single keys only.
+    pub fn matches(&self, mode: KeyMode, action: &str, k: &KeyEvent) -> bool {
+        let chord = Chord::from_event(k);
+        self.bindings
+            .get(&mode)
+            .into_iter()
+            .flatten()
+            .any(|(s, a)| a == action && s.as_slice() == [chord])
+    }
+
+    /// "press 'v'" for hints, or "run :verify" if `action` has no key.
+    pub fn press(&self, mode: KeyMode, action: &str) -> String {
+        match self.keys_for(mode, action).first() {
+            Some(key) => format!("press '{key}'"),
+            None => format!("run :{action}"),
+        }
+    }
+
+    /// "j/Down down, i insert, ..." for the given (action, label) pairs; unbound
+    /// actions are left out.
+    pub fn help(&self, mode: KeyMode, items: &[(&str, &str)]) -> String {
+        items
+            .iter()
+            .filter_map(|(action, label)| {
+                let keys = self.keys_for(mode, action);
+                (!keys.is_empty()).then(|| format!("{} {label}", keys.join("/")))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Every bound action of `mode` with its keys, in binding order.
+    pub fn actions(&self, mode: KeyMode) -> Vec<(String, Vec<String>)> {
+        let mut out: Vec<(String, Vec<String>)> = Vec::new();
+        for (seq, action) in self.bindings.get(&mode).into_iter().flatten() {
+            match out.iter_mut().find(|(a, _)| a == action) {
+                Some((_, keys)) => keys.push(seq_label(seq)),
+                None => out.push((action.clone(), vec![seq_label(seq)])),
+            }
+        }
+        out
+    }
+}
+
+fn validate_action(mode: KeyMode, action: &str) -> Result<()> {
+    if mode.actions().contains(&action) {
+        return Ok(());
+    }
+    if mode == KeyMode::Normal {
+        // Any command line, e.g. "open Alice" or "timer 1d".
+        let (name, _) = commands::split(action);
+        commands::find(name).map_err(anyhow::Error::msg)?;
+        return Ok(());
+    }
+    bail!("unknown action {action:?} (expected one of: {})", mode.actions().join(", "))
+}
+
+fn seq_label(seq: &[Chord]) -> String {
+    let labels: Vec<String> = seq.iter().map(Chord::label).collect();
+    // "gg" reads better than "g g"; named keys need a separator.
+    if labels.iter().all(|l| l.chars().count() == 1) {
+        labels.concat()
+    } else {
+        labels.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(code: KeyCode, mods: KeyModifiers) -> Chord {
+        Chord::new(code, mods)
+    }
+
+    fn keymap(mode: &str, entries: &[(&str, &str)]) -> Result<Keymap> {
+        let table = entries.iter().map(|(k, a)| (k.to_string(), a.to_string())).collect();
+        Keymap::from_config(&HashMap::from([(mode.to_string(), table)]))
+    }
+
+    fn press(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn parse_plain_and_bracketed_keys() {
+        let none = KeyModifiers::NONE;
+        assert_eq!(
+            parse_sequence("gg").unwrap(),
+            [chord(KeyCode::Char('g'), none), chord(KeyCode::Char('g'), none)]
+        );
+        assert_eq!(parse_sequence("G").unwrap(), [chord(KeyCode::Char('G'), KeyModifiers::SHIFT)]);
+        assert_eq!(parse_sequence("<C-P>").unwrap(), [chord(KeyCode::Char('p'), KeyModifiers::CONTROL)]);
+        assert_eq!(parse_sequence("<A-Enter>").unwrap(), [chord(KeyCode::Enter, KeyModifiers::ALT)]);
+        assert_eq!(parse_sequence("<M-cr>").unwrap(), [chord(KeyCode::Enter, KeyModifiers::ALT)]);
+        assert_eq!(parse_sequence("<S-Tab>").unwrap(), [chord(KeyCode::Tab, KeyModifiers::SHIFT)]);
+        assert_eq!(parse_sequence("<F5>").unwrap(), [chord(KeyCode::F(5), none)]);
+        assert_eq!(parse_sequence("<lt>").unwrap(), [chord(KeyCode::Char('<'), none)]);
+        assert_eq!(parse_sequence("<->").unwrap(), [chord(KeyCode::Char('-'), none)]);
+        assert_eq!(
+            parse_sequence("<Space>x").unwrap(),
+            [chord(KeyCode::Char(' '), none), chord(KeyCode::Char('x'), none)]
+        );
+        // No closing bracket: a plain `<`.
+        assert_eq!(parse_sequence("<").unwrap(), [chord(KeyCode::Char('<'), none)]);
+    }
+
+    #[test]
+    fn parse_rejects_bad_keys() {
+        assert!(parse_sequence("").is_err());
+        assert!(parse_sequence("<Nope>").is_err());
+        assert!(parse_sequence("<X-a>").is_err());
+    }
+
+    #[test]
+    fn config_overrides_and_unbinds_defaults() {
+        let keys = keymap("normal", &[("<C-n>", "down"), ("j", ""), ("q", "none")]).unwrap();
+        assert_eq!(keys.keys_for(KeyMode::Normal, "down"), ["Down", "Ctrl-n"]);
+        assert!(keys.keys_for(KeyMode::Normal, "quit").is_empty());
+        assert!(!keys.matches(KeyMode::Normal, "down", &press('j')));
+        assert_eq!(keys.press(KeyMode::Normal, "quit"), "run :quit");
+        assert_eq!(keys.press(KeyMode::Normal, "verify"), "press 'v'");
+    }
+
+    #[test]
+    fn config_rejects_the_same_key_twice() {
+        let err = keymap("normal", &[("<C-n>", "down"), ("<C-N>", "up")]).unwrap_err();
+        assert!(format!("{err:#}").contains("are the same key"), "{err:#}");
+    }
+
+    #[test]
+    fn config_rejects_keys_hiding_sequences() {
+        // `g` would fire before the default `gg` could.
+        let err = keymap("normal", &[("g", "top")]).unwrap_err();
+        assert!(format!("{err:#}").contains("g (top) hides gg (top)"), "{err:#}");
+        // Fine once `gg` is unbound.
+        assert!(keymap("normal", &[("g", "top"), ("gg", "")]).is_ok());
+    }
+
+    #[test]
+    fn config_rejects_unknown_modes_and_actions() {
+        assert!(keymap("visual", &[("x", "down")]).is_err());
+        assert!(keymap("insert", &[("<C-s>", "down")]).is_err());
+        assert!(keymap("normal", &[("x", "frobnicate")]).is_err());
+        assert!(keymap("normal", &[("<C-o>", "open Alice")]).is_ok());
+        assert!(keymap("popup", &[("v", "trust")]).is_ok());
+        assert!(keymap("popup", &[("x", "quit")]).is_err());
+        assert!(keymap("finder", &[("<C-j>", "down")]).is_ok());
+    }
+
+    #[test]
+    fn popups_take_single_keys() {
+        let err = keymap("popup", &[("tt", "trust")]).unwrap_err();
+        assert!(format!("{err:#}").contains("single keys"), "{err:#}");
+        let keys = Keymap::default();
+        assert!(keys.matches(KeyMode::Popup, "cancel", &press('q')));
+        assert!(keys.matches(KeyMode::Finder, "up", &KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL)));
+        // The finder takes typed text, so plain letters stay unbound there.
+        assert!(!keys.matches(KeyMode::Finder, "down", &press('j')));
+    }
+
+    #[test]
+    fn feed_resolves_sequences() {
+        let keys = Keymap::default();
+        let mut pending = Vec::new();
+        let fed = keys.feed(KeyMode::Normal, &mut pending, press('g'));
+        assert!(fed.action.is_none() && fed.unbound.is_empty());
+        let fed = keys.feed(KeyMode::Normal, &mut pending, press('g'));
+        assert_eq!(fed.action.as_deref(), Some("top"));
+        // A broken-off sequence hands its keys back and starts over.
+        keys.feed(KeyMode::Normal, &mut pending, press('g'));
+        let fed = keys.feed(KeyMode::Normal, &mut pending, press('j'));
+        assert_eq!(fed.action.as_deref(), Some("down"));
+        assert_eq!(fed.unbound, [press('g')]);
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::Child;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyModifiers},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use qrcode::QrCode;
+use ratatui::{
+    Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::signal_cli::SignalCli;
+
+enum LinkEvent {
+    Uri(String),
+    Output(String),
+}
+
+struct LinkState {
+    uri: Option<String>,
+    qr: Vec<String>,
+    last_output: Option<String>,
+}
+
+/// First-run onboarding: runs `signal-cli link`, shows the device-link URI as a
+/// QR code and returns once the phone has accepted it.
+pub fn run(signal: &SignalCli, device_name: &str) -> Result<()> {
+    let mut child = LinkChild(signal.spawn_link(device_name)?);
+    let child = &mut child.0;
+    let stdout = child.stdout.take().context("signal-cli link stdout")?;
+    let mut stderr = child.stderr.take().context("signal-cli link stderr")?;
+
+    let (tx, rx) = mpsc::channel::<LinkEvent>();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break; };
+            let line = line.trim().to_string();
+            let ev = if line.starts_with("sgnl://") || line.starts_with("tsdevice:") {
+                LinkEvent::Uri(line)
+            } else {
+                LinkEvent::Output(line)
+            };
+            if tx.send(ev).is_err() {
+                break;
+            }
+        }
+    });
+    let stderr_reader = thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stderr.read_to_string(&mut buf);
+        buf
+    });
+
+    enable_raw_mode().context("enable raw mode")?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen).context("enter alt screen")?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend).context("create terminal")?;
+
+    let mut state = LinkState { uri: None, qr: Vec::new(), last_output: None };
+    let res = (|| -> Result<()> {
+        loop {
+            while let Ok(ev) = rx.try_recv() {
+                match ev {
+                    LinkEvent::Uri(uri) => {
+                        state.qr = qr_lines(&uri)?;
+                        state.uri = Some(uri);
+                    }
+                    LinkEvent::Output(line) if !line.is_empty() => state.last_output = Some(line),
+                    LinkEvent::Output(_) => {}
+                }
+            }
+
+            if let Some(status) = child.try_wait().context("wait for signal-cli link")? {
+                if status.success() {
+                    return Ok(());
+                }
+                let err = stderr_reader.join().unwrap_or_default();
+                bail!("signal-cli link failed (code={:?}): {}", status.code(), err.trim());
+            }
+
+            terminal.draw(|f| draw(f, &state))?;
+
+            if event::poll(Duration::from_millis(200)).context("poll events")?
+                && let Event::Key(k) = event::read().context("read event")?
+            {
+                let ctrl_c = k.code == KeyCode::Char('c') && k.modifiers.contains(KeyModifiers::CONTROL);
+                if ctrl_c || matches!(k.code, KeyCode::Esc | KeyCode::Char('q')) {
+                    bail!("device linking cancelled");
+                }
+            }
+        }
+    })();
+
+    disable_raw_mode().ok();
+    execute!(terminal.backend_mut(), LeaveAlternateScreen).ok();
+    terminal.show_cursor().ok();
+
+    res
+}
+
+/// Kills and reaps `signal-cli link` however `run` returns; a no-op once it
+/// exited on its own.
+struct LinkChild(Child);
+
+impl Drop for LinkChild {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn draw(f: &mut Frame, state: &LinkState) {
+    let area = f.area();
+    let mut lines: Vec<Line> = Vec::new();
+    if state.uri.is_none() {
+        lines.push(Line::raw("Waiting for signal-cli to generate a link code..."));
+    } else {
+        // Explicit colors so the code scans on both light and dark terminals.
+        let qr_style = Style::default().fg(Color::Black).bg(Color::White);
+        for row in &state.qr {
+            lines.push(Line::styled(row.clone(), qr_style));
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::raw("On your phone: Signal > Settings > Linked devices > Link new device"));
+    }
+    if let Some(out) = &state.last_output {
+        lines.push(Line::styled(out.clone(), Style::default().fg(Color::Gray)));
+    }
+    lines.push(Line::styled("Esc: cancel", Style::default().fg(Color::Gray)));
+
+    let qr_width = state.qr.first().map(|r| r.chars().count()).unwrap_or(0) as u16;
+    let need = (qr_width.saturating_add(2), (lines.len() as u16).saturating_add(2));
+    if state.uri.is_some() && (area.width < need.0 || area.height < need.1) {
+        // A wrapped or clipped code does not scan.
+        let msg = format!(
+            "Terminal too small for the link code: need {}x{}, have {}x{}. Enlarge it or zoom out.",
+            need.0, need.1, area.width, area.height
+        );
+        let p = Paragraph::new(msg).wrap(Wrap { trim: true });
+        f.render_widget(Clear, area);
+        f.render_widget(p, area);
+        return;
+    }
+    let width = qr_width.max(70).saturating_add(4).min(area.width);
+    let height = need.1.min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let p = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title("Link this device"));
+    f.render_widget(Clear, popup);
+    f.render_widget(p, popup);
+}
+
+/// Renders `data` as a QR code using Unicode half blocks (two modules per cell).
+/// Dark modules are drawn in the foreground color, so render with fg=black/bg=white.
+fn qr_lines(data: &str) -> Result<Vec<String>> {
+    // The spec asks for a 4-module quiet zone; phone cameras miss narrower ones.
+    const QUIET: usize = 4;
+    let code = QrCode::new(data.as_bytes()).context("encode link URI as QR code")?;
+    let width = code.width();
+    let colors = code.to_colors();
+    let dark = |x: usize, y: usize| -> bool {
+        if x < QUIET || y < QUIET || x >= width + QUIET || y >= width + QUIET {
+            return false;
+        }
+        colors[(y - QUIET) * width + (x - QUIET)] == qrcode::Color::Dark
+    };
+
+    let size = width + 2 * QUIET;
+    let mut out = Vec::with_capacity(size.div_ceil(2));
+    for y in (0..size).step_by(2) {
+        let mut row = String::with_capacity(size);
+        for x in 0..size {
+            row.push(match (dark(x, y), dark(x, y + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        out.push(row);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    const URI: &str = "sgnl://linkdevice?uuid=AbCdEfGhIjKlMnOp&pub_key=QrStUvWxYz0123456789";
+
+    fn screen(state: &LinkState, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| draw(f, state)).unwrap();
+        let buf = terminal.backend().buffer();
+        buf.content.iter().map(|c| c.symbol()).collect()
+    }
+
+    #[test]
+    fn qr_packs_two_modules_per_row_inside_a_quiet_zone() {
+        let modules = QrCode::new(URI.as_bytes()).unwrap().width();
+        let lines = qr_lines(URI).unwrap();
+        let size = modules + 8;
+        assert_eq!(lines.len(), size.div_ceil(2));
+        assert!(lines.iter().all(|l| l.chars().count() == size));
+        // 4 blank modules all around: two blank rows on top, four blank columns.
+        assert!(lines[..2].iter().all(|l| l.trim().is_empty()));
+        for l in &lines {
+            assert!(l.starts_with("    ") && l.ends_with("    "), "{l:?}");
+        }
+        // The top-left finder pattern starts right after the quiet zone.
+        assert_eq!(lines[2].chars().nth(4), Some('█'));
+    }
+
+    #[test]
+    fn refuses_a_terminal_too_small_for_the_code() {
+        let state = LinkState { uri: Some(URI.to_string()), qr: qr_lines(URI).unwrap(), last_output: None };
+        let need = state.qr[0].chars().count() as u16 + 2;
+        let small = screen(&state, need - 1, 80);
+        assert!(small.contains("Terminal too small for the link code"), "{small}");
+        let fits = screen(&state, need.max(74), 80);
+        assert!(!fits.contains("Terminal too small") && fits.contains("Link new device"));
+        assert!(fits.contains('█'));
+    }
+}
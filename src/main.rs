@@ -1,24 +1,44 @@
 mod signal_cli;
+mod commands;
 mod config;
+mod editor;
+mod fuzzy;
+mod graphics;
+mod history;
+mod hooks;
+mod keys;
+mod link;
+mod notify;
 mod scrollback;
+mod state;
+mod text_style;
+mod theme;
 
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::io;
-use std::io::IsTerminal;
+use std::io::{IsTerminal, Write};
 use std::sync::{
     Arc,
     atomic::{AtomicBool, Ordering},
     mpsc,
 };
+use std::path::PathBuf;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result, bail};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
-    execute,
+    cursor::{MoveTo, RestorePosition, SavePosition},
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
     terminal::{
         EnterAlternateScreen, LeaveAlternateScreen, SetTitle, disable_raw_mode, enable_raw_mode,
+        supports_keyboard_enhancement,
     },
 };
 use ratatui::{
@@ -28,17 +48,34 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
 };
 
-use crate::signal_cli::{IncomingMessage, SignalCli};
+use crate::editor::Editor;
+use crate::commands::Cmd;
+use crate::history::{History, HistoryList};
+use crate::keys::{KeyMode, Keymap};
+use crate::notify::{ChatRule, Mute};
+use crate::signal_cli::{
+    Attachment, CallEvent, CallUpdate, Identity, Received, SignalCli, Sticker, StickerPack, Story,
+};
 use crate::scrollback::ScrollbackRecord;
+use crate::text_style::StyleRange;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Mode {
     Normal,
     Insert,
     AddRecipient,
+    Identity,
+    AccountPicker,
+    SetTimer,
+    StickerPicker,
+    Stories,
+    // `:` command line.
+    Command,
+    // Fuzzy chat finder popup.
+    Finder,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -54,6 +91,9 @@ struct Target {
     // For contacts: E.164 number. For groups: group id.
     addr: String,
     display: String,
+    // Contact/profile or group name from signal-cli, kept for the finder
+    // when an alias is displayed instead.
+    name: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -62,44 +102,191 @@ struct ChatMessage {
     dir: MsgDir,
     who: Option<String>,
     body: String,
+    // Disappearing messages: the chat's timer, and when the message is
+    // removed locally. Incoming timers only start once the chat is read.
+    expires_in_secs: u32,
+    expires_at_ms: Option<i64>,
+    // Unopened view-once media, one file per attachment; empty once viewed.
+    view_once: Vec<PathBuf>,
+    styles: Vec<StyleRange>,
+    // Set for stickers; `body` is then their text label.
+    sticker: Option<Sticker>,
 }
 
 #[derive(Debug, Clone)]
 enum MsgDir {
     In,
     Out,
+    // Generated by us (missed calls etc.), not sent by anyone.
+    System,
 }
 
-struct App {
+// Everything that belongs to one signal-cli account.
+struct AccountState {
     account: String,
-    cfg: config::Config,
-    notify_send: bool,
-    mode: Mode,
+    // Our own UUID, if signal-cli knows it; mentions may name us by it.
+    uuid: Option<String>,
     targets: Vec<Target>,
     selected: usize,
-    pending_g: bool,
     unread: HashMap<String, usize>,
+    messages: HashMap<String, Vec<ChatMessage>>,
+    // Conversation key -> warning about an untrusted/changed identity key.
+    identity_alerts: HashMap<String, String>,
+    // Conversation key -> disappearing-message timer in seconds (absent = off),
+    // persisted in the state dir.
+    timers: HashMap<String, u32>,
+    // Known sticker packs; loaded when the first sticker arrives (for its pack
+    // title) and refreshed when the sticker picker opens.
+    sticker_packs: Option<Vec<StickerPack>>,
+    // Unexpired stories, memory only (they vanish after 24h anyway).
+    stories: Vec<Story>,
+    // Call ids already offered, so re-sent offers do not ring again.
+    seen_calls: HashSet<u64>,
+    // Call id -> (when the offer arrived, the offer), until answered or missed.
+    pending_calls: HashMap<u64, (i64, CallEvent)>,
+    // Conversation key -> unsent composer text (persisted in the state dir
+    // unless `keeps_on_disk` says otherwise).
+    drafts: HashMap<String, String>,
+    // Conversation key -> notification settings changed with :mute etc. (persisted).
+    notify_rules: HashMap<String, ChatRule>,
+    // Conversation key -> time of the latest message (ms), for sorting by activity.
+    last_activity: HashMap<String, i64>,
+    // Conversation keys kept at the top of the list (persisted in the state dir).
+    pinned: Vec<String>,
+    // Conversation keys hidden from the list until a new message arrives (persisted).
+    archived: Vec<String>,
+    // Chats opened by name this session; listed even if empty or archived.
+    revealed: HashSet<String>,
+    // The list shows `targets[..shown]`; the rest are filtered out (see `sort_targets`).
+    shown: usize,
+}
+
+impl AccountState {
+    fn selected_target(&self) -> Option<&Target> {
+        self.targets[..self.shown].get(self.selected)
+    }
+}
+
+// What the chat list shows: its order plus the session's filter toggles.
+#[derive(Debug, Clone, Copy)]
+struct ChatView {
+    sort: config::ChatSort,
+    // Also list recipients we never exchanged a message with.
+    all: bool,
+    // List the archived chats instead of the others.
+    archived: bool,
+}
+
+struct App {
+    cfg: config::Config,
+    // Desktop notifications; None if disabled or no notification server.
+    notifier: Option<notify::Notifier>,
+    // `[hooks] on_message`.
+    hooks: Option<hooks::Hooks>,
+    mode: Mode,
+    accounts: Vec<AccountState>,
+    active: usize,
+    // Cursor in the account picker (`Mode::AccountPicker`).
+    account_cursor: usize,
+    // Keys typed so far of a multi-key binding (e.g. the first `g` of `gg`).
+    pending_keys: Vec<KeyEvent>,
     title_dirty: bool,
-    input: String,
+    input: Editor,
     status: String,
-    messages: HashMap<String, Vec<ChatMessage>>,
+    // Identities of the selected contact while in `Mode::Identity`.
+    identities: Vec<Identity>,
+    // Viewed view-once files still waiting to be deleted.
+    wipe_on_exit: Vec<PathBuf>,
+    // Installed stickers, flattened, while in `Mode::StickerPicker`.
+    sticker_choices: Vec<Sticker>,
+    sticker_cursor: usize,
+    // Conversation keys of the chats matching the finder query, best first;
+    // keys rather than indices, as the chat list re-sorts under the popup.
+    finder_matches: Vec<String>,
+    finder_cursor: usize,
+    // Conversations whose spoilers are currently shown.
+    revealed_spoilers: HashSet<String>,
+    // Lowercased `:search` text highlighted in the chat.
+    chat_search: Option<String>,
+    story_cursor: usize,
+    // Set while composing a reply to this story.
+    story_reply: Option<Story>,
+    // Set by a key handler; the run loop suspends the TUI and opens $EDITOR.
+    external_edit: bool,
+    history: History,
+    // While recalling with Up/Down: (history index, text typed before recalling).
+    history_nav: Option<(usize, String)>,
+    history_search: Option<HistorySearch>,
+    show_all: bool,
+    show_archived: bool,
+    // The terminal reports modified keys like Shift-Enter (kitty keyboard protocol).
+    keyboard_enhanced: bool,
+    // Sticker images by (pack id, sticker id), fetched in the background.
+    sticker_images: HashMap<(String, u32), StickerImage>,
+    // Kitty keeps uploaded images; these were sent already.
+    sticker_uploaded: HashSet<(String, u32)>,
+    // Frame size and images drawn after the last frame; redrawn only on change.
+    drawn_images: (Rect, Vec<ImagePlacement>),
+}
+
+enum StickerImage {
+    Loading,
+    Ready(graphics::Image),
+    // No graphics for this one (fetch or decode failed); the label stays.
+    Failed,
+}
+
+// A sticker image's top-left cell, from `draw_chat`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ImagePlacement {
+    x: u16,
+    y: u16,
+    sticker: (String, u32),
+}
+
+// Ctrl-r reverse search through the current prompt's history.
+struct HistorySearch {
+    query: String,
+    // Index of the current match.
+    hit: Option<usize>,
+    // Composer text from before the search, restored on Esc.
+    stash: String,
 }
 
 impl App {
+    fn acct(&self) -> &AccountState {
+        &self.accounts[self.active]
+    }
+
+    fn acct_mut(&mut self) -> &mut AccountState {
+        &mut self.accounts[self.active]
+    }
+
     fn selected_target(&self) -> Option<&Target> {
-        self.targets.get(self.selected)
+        self.acct().selected_target()
+    }
+
+    fn chat_view(&self) -> ChatView {
+        ChatView { sort: self.cfg.sort, all: self.show_all, archived: self.show_archived }
     }
 }
 
 enum BgEvent {
-    Received(Vec<IncomingMessage>),
-    Error(String),
+    // Index into `App::accounts`.
+    Received(usize, Received),
+    Error(usize, String),
+    // A notification action was invoked: (notification id, action key).
+    NotificationAction(u32, String),
+    // A sticker image finished loading; None if it cannot be shown.
+    StickerImage((String, u32), Option<graphics::Image>),
 }
 
 fn main() -> Result<()> {
     let args = parse_args();
     if args.help {
-        print_help();
+        // Show the configured bindings; fall back to the defaults if the config is broken.
+        let keys = config::load_keys(args.config.clone().map(Into::into)).unwrap_or_default();
+        print_help(&keys);
         return Ok(());
     }
     if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
@@ -109,109 +296,268 @@ fn main() -> Result<()> {
     let cfg = config::load_or_create(args.config.clone().map(Into::into)).context("load config")?;
     let signal = SignalCli::with_bin(args.bin);
 
-    let accounts = signal.list_accounts().context("list signal-cli accounts")?;
-    let account = if let Some(a) = args.account {
-        a
+    let mut accounts = signal.list_accounts().context("list signal-cli accounts")?;
+    if accounts.is_empty() && args.account.is_none() {
+        // First run: offer to link this machine to an existing phone.
+        link::run(&signal, "signal-tui").context("link device")?;
+        accounts = signal.list_accounts().context("list signal-cli accounts")?;
+    }
+    if let Some(a) = &args.account
+        && !accounts.contains(a)
+    {
+        accounts.insert(0, a.clone());
+    }
+    if accounts.is_empty() {
+        bail!("no signal-cli accounts found (try `signal-cli register` / `signal-cli link` first)");
+    }
+
+    // Older versions kept one flat scrollback dir for whichever account was in use;
+    // that was `--account` or else the first one signal-cli lists.
+    let legacy_owner = args.account.as_deref().unwrap_or(&accounts[0]);
+    // Shown in the status line; stderr is hidden behind the TUI.
+    let migrated = match scrollback::migrate_flat_layout(&cfg.scrollback_dir, legacy_owner) {
+        Ok(0) => None,
+        Ok(n) => Some(format!("moved {n} scrollback file(s) to the {legacy_owner} account")),
+        Err(e) => Some(format!("scrollback migration failed: {e:#}")),
+    };
+
+    let states: Vec<AccountState> = accounts
+        .iter()
+        .map(|a| load_account(&signal, &cfg, a))
+        .collect();
+    let active = args
+        .account
+        .as_ref()
+        .and_then(|a| accounts.iter().position(|x| x == a))
+        .unwrap_or(0);
+
+    // Ask which account to open unless it was given on the command line.
+    let (mode, mut status) = if args.account.is_none() && accounts.len() > 1 {
+        (
+            Mode::AccountPicker,
+            format!("found {} accounts; pick one (Enter), 'A' switches later", accounts.len()),
+        )
     } else {
-        accounts.first().cloned().unwrap_or_default()
+        (Mode::Normal, format!("using account {}", accounts[active]))
     };
-    if account.is_empty() {
-        bail!("no signal-cli accounts found (try `signal-cli register` / `signal-cli link` first)");
+    if let Some(m) = migrated {
+        status = format!("{m}; {status}");
+    }
+
+    let history = History::load(&cfg.state_dir, cfg.save_scrollback);
+    let mut app = App {
+        cfg,
+        notifier: None,
+        hooks: None,
+        mode,
+        accounts: states,
+        active,
+        account_cursor: active,
+        pending_keys: Vec::new(),
+        title_dirty: true,
+        input: Editor::default(),
+        status,
+        identities: Vec::new(),
+        wipe_on_exit: Vec::new(),
+        sticker_choices: Vec::new(),
+        sticker_cursor: 0,
+        finder_matches: Vec::new(),
+        finder_cursor: 0,
+        revealed_spoilers: HashSet::new(),
+        chat_search: None,
+        story_cursor: 0,
+        story_reply: None,
+        external_edit: false,
+        history,
+        history_nav: None,
+        history_search: None,
+        show_all: false,
+        show_archived: false,
+        keyboard_enhanced: false,
+        sticker_images: HashMap::new(),
+        sticker_uploaded: HashSet::new(),
+        drawn_images: (Rect::default(), Vec::new()),
+    };
+
+    if app.cfg.notify {
+        // Without a session bus or notification server there are just no popups.
+        app.notifier = notify::Notifier::connect().ok();
+    }
+    app.hooks = app.cfg.hooks.clone().map(hooks::Hooks::new);
+    let view = app.chat_view();
+    for acct in &mut app.accounts {
+        load_initial_scrollback(&app.cfg, acct).ok();
+        sort_targets(acct, view);
+        // Timers set while signal-tui was not running.
+        for (key, _) in acct.timers.iter().filter(|&(_, &t)| t > 0) {
+            forget_history(&mut app.history, &mut app.status, &acct.account, key);
+        }
+        // Drafts saved before `save_scrollback` was turned off or a timer was set.
+        if acct.drafts.keys().any(|k| !keeps_on_disk(&app.cfg, acct, k))
+            && let Err(e) = persist_drafts(&app.cfg, acct)
+        {
+            app.status = format!("save draft: {e:#}");
+        }
     }
+    // The chat selected at startup is on screen, so its messages count as read.
+    if let Some(key) = app.selected_target().map(|t| t.conversation_key.clone()) {
+        start_timers(&mut app, &key);
+    }
+
+    run_tui(&signal, &mut app)
+}
 
+fn load_account(signal: &SignalCli, cfg: &config::Config, account: &str) -> AccountState {
     let mut targets = Vec::new();
-    for c in signal.list_contacts(&account).unwrap_or_default() {
+    for c in signal.list_contacts(account).unwrap_or_default() {
         let display = cfg
             .aliases
             .get(&c.number)
             .cloned()
-            .or(c.name)
+            .or_else(|| c.name.clone())
             .unwrap_or_else(|| c.number.clone());
         targets.push(Target {
             conversation_key: format!("contact:{}", c.number),
             kind: TargetKind::Contact,
             addr: c.number,
             display,
+            name: c.name,
         });
     }
-    for g in signal.list_groups(&account).unwrap_or_default() {
-        let display = g.name.unwrap_or_else(|| format!("group {}", g.id));
+    for g in signal.list_groups(account).unwrap_or_default() {
+        let display = g.name.clone().unwrap_or_else(|| format!("group {}", g.id));
         targets.push(Target {
             conversation_key: format!("group:{}", g.id),
             kind: TargetKind::Group,
             addr: g.id,
             display,
+            name: g.name,
         });
     }
-    targets.sort_by(|a, b| a.display.to_lowercase().cmp(&b.display.to_lowercase()));
-
-    let status = if accounts.len() > 1 {
-        format!(
-            "using account {account} (found {} accounts; no selector yet)",
-            accounts.len()
-        )
-    } else {
-        format!("using account {account}")
-    };
-
-    let mut app = App {
-        account,
-        cfg,
-        notify_send: false,
-        mode: Mode::Normal,
+    AccountState {
+        account: account.to_string(),
+        uuid: signal.account_uuid(account).ok().flatten(),
         targets,
         selected: 0,
-        pending_g: false,
         unread: HashMap::new(),
-        title_dirty: true,
-        input: String::new(),
-        status,
         messages: HashMap::new(),
-    };
-
-    app.notify_send = app.cfg.notify && notify_send_available();
-    load_initial_scrollback(&mut app).ok();
-
-    run_tui(&signal, &mut app)
+        identity_alerts: HashMap::new(),
+        timers: state::load_timers(&cfg.state_dir, account),
+        sticker_packs: None,
+        stories: Vec::new(),
+        seen_calls: HashSet::new(),
+        pending_calls: HashMap::new(),
+        drafts: state::load_drafts(&cfg.state_dir, account),
+        notify_rules: state::load_notify_rules(&cfg.state_dir, account),
+        last_activity: HashMap::new(),
+        pinned: state::load_pinned(&cfg.state_dir, account),
+        archived: state::load_archived(&cfg.state_dir, account),
+        revealed: HashSet::new(),
+        shown: 0,
+    }
 }
 
 fn run_tui(signal: &SignalCli, app: &mut App) -> Result<()> {
     enable_raw_mode().context("enable raw mode")?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen).context("enter alt screen")?;
+    // Without this, Shift-Enter arrives as a plain Enter and sends the message.
+    app.keyboard_enhanced = supports_keyboard_enhancement().unwrap_or(false);
+    if app.keyboard_enhanced {
+        execute!(stdout, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES))
+            .context("enable keyboard enhancement")?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend).context("create terminal")?;
 
     let stop = Arc::new(AtomicBool::new(false));
     let (tx, rx) = mpsc::channel::<BgEvent>();
-    let account = app.account.clone();
-    let signal2 = signal.clone();
-    let stop2 = stop.clone();
-    let bg = thread::spawn(move || {
-        while !stop2.load(Ordering::Relaxed) {
-            match signal2.receive_once(&account, 1) {
-                Ok(msgs) => {
-                    if !msgs.is_empty() {
-                        let _ = tx.send(BgEvent::Received(msgs));
+    if let Some(n) = &app.notifier {
+        let tx = tx.clone();
+        if let Err(e) = n.listen(move |id, action| {
+            let _ = tx.send(BgEvent::NotificationAction(id, action));
+        }) {
+            app.status = format!("{e:#}");
+        }
+    }
+    // One receiver per account so every account gets messages, not just the open one.
+    let mut workers = Vec::new();
+    let (sticker_tx, sticker_rx) = mpsc::channel::<(String, Sticker)>();
+    if let Some(protocol) = app.cfg.graphics {
+        // Fetching and decoding takes a moment per sticker; the label shows meanwhile.
+        let signal2 = signal.clone();
+        let tx = tx.clone();
+        let cell = graphics::cell_size();
+        workers.push(thread::spawn(move || {
+            // Ends when the run loop drops `sticker_tx`.
+            for (account, st) in sticker_rx {
+                let img = signal2
+                    .get_sticker(&account, &st.pack_id, st.sticker_id)
+                    .and_then(|b| graphics::Image::decode(protocol, &b, graphics::STICKER_ROWS, cell))
+                    .ok();
+                let _ = tx.send(BgEvent::StickerImage((st.pack_id, st.sticker_id), img));
+            }
+        }));
+    }
+    for (idx, acct) in app.accounts.iter().enumerate() {
+        let account = acct.account.clone();
+        let signal2 = signal.clone();
+        let stop2 = stop.clone();
+        let tx = tx.clone();
+        workers.push(thread::spawn(move || {
+            while !stop2.load(Ordering::Relaxed) {
+                match signal2.receive_once(&account, 1) {
+                    Ok(rx) => {
+                        if !rx.is_empty() {
+                            let _ = tx.send(BgEvent::Received(idx, rx));
+                        }
+                    }
+                    Err(e) => {
+                        let _ = tx.send(BgEvent::Error(idx, format!("{e:#}")));
+                        thread::sleep(Duration::from_secs(2));
                     }
-                }
-                Err(e) => {
-                    let _ = tx.send(BgEvent::Error(format!("{e:#}")));
-                    thread::sleep(Duration::from_secs(2));
                 }
             }
-        }
-    });
+        }));
+    }
 
     let res = (|| -> Result<()> {
+        let mut last_expiry_check = Instant::now();
         loop {
+            if last_expiry_check.elapsed() >= Duration::from_secs(1) {
+                expire_messages(app);
+                expire_calls(app);
+                expire_mutes(app);
+                last_expiry_check = Instant::now();
+            }
+            if let Some(e) = app.notifier.as_mut().and_then(|n| n.poll()) {
+                app.status = format!("{e:#}");
+            }
+
             while let Ok(ev) = rx.try_recv() {
                 match ev {
-                    BgEvent::Received(msgs) => {
-                        ingest_incoming(app, msgs);
+                    BgEvent::Received(idx, rx) => {
+                        load_sticker_packs_for(signal, &mut app.accounts[idx], &rx);
+                        ingest_incoming(app, idx, rx);
                         app.title_dirty = true;
                     }
-                    BgEvent::Error(e) => app.status = format!("receive error: {e}"),
+                    BgEvent::Error(idx, e) => {
+                        app.status = if app.accounts.len() > 1 {
+                            format!("receive error ({}): {e}", app.accounts[idx].account)
+                        } else {
+                            format!("receive error: {e}")
+                        };
+                    }
+                    BgEvent::NotificationAction(id, action) => {
+                        let chat = app.notifier.as_mut().and_then(|n| n.chat_of(id));
+                        if let Some((account, key)) = chat {
+                            focus_chat(app, &account, &key, action == "reply");
+                        }
+                    }
+                    BgEvent::StickerImage(id, img) => {
+                        let img = img.map_or(StickerImage::Failed, StickerImage::Ready);
+                        app.sticker_images.insert(id, img);
+                    }
                 }
             }
 
@@ -220,22 +566,49 @@ fn run_tui(signal: &SignalCli, app: &mut App) -> Result<()> {
                 app.title_dirty = false;
             }
 
-            terminal.draw(|f| ui(f, app))?;
+            if app.external_edit {
+                app.external_edit = false;
+                compose_in_editor(&mut terminal, app);
+                // The screen was cleared; draw and (kitty) upload the images again.
+                app.drawn_images = (Rect::default(), Vec::new());
+                app.sticker_uploaded.clear();
+            }
+
+            request_sticker_images(app, &sticker_tx);
+            let mut images = (Rect::default(), Vec::new());
+            terminal.draw(|f| images = (f.area(), ui(f, app)))?;
+            draw_images(&mut terminal, app, images)?;
 
-            if event::poll(Duration::from_millis(200)).context("poll events")? {
-                if let Event::Key(k) = event::read().context("read event")? {
-                    if handle_key(signal, app, k)? {
-                        break;
-                    }
-                }
+            if event::poll(Duration::from_millis(200)).context("poll events")?
+                && let Event::Key(k) = event::read().context("read event")?
+                && handle_key(signal, app, k)?
+            {
+                break;
             }
         }
         Ok(())
     })();
 
+    // Quitting mid-message keeps what was typed.
+    if app.mode == Mode::Insert {
+        stash_draft(app);
+    }
+
     stop.store(true, Ordering::Relaxed);
-    let _ = bg.join();
+    drop(sticker_tx);
+    for w in workers {
+        let _ = w.join();
+    }
+    for p in &app.wipe_on_exit {
+        let _ = fs::remove_file(p);
+    }
 
+    if app.cfg.graphics == Some(graphics::Protocol::Kitty) {
+        let _ = terminal.backend_mut().write_all(graphics::KITTY_DELETE_ALL.as_bytes());
+    }
+    if app.keyboard_enhanced {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags).ok();
+    }
     disable_raw_mode().ok();
     execute!(terminal.backend_mut(), LeaveAlternateScreen).ok();
     terminal.show_cursor().ok();
@@ -243,6 +616,72 @@ fn run_tui(signal: &SignalCli, app: &mut App) -> Result<()> {
     res
 }
 
+/// Suspends the TUI (same teardown as `run_tui`), edits the composer text in
+/// $VISUAL/$EDITOR and puts the result back in the composer for review.
+fn compose_in_editor(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App) {
+    if app.keyboard_enhanced {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags).ok();
+    }
+    disable_raw_mode().ok();
+    execute!(terminal.backend_mut(), LeaveAlternateScreen).ok();
+    terminal.show_cursor().ok();
+
+    let res = edit_externally(app.input.text());
+
+    enable_raw_mode().ok();
+    execute!(terminal.backend_mut(), EnterAlternateScreen).ok();
+    if app.keyboard_enhanced {
+        execute!(terminal.backend_mut(), PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES))
+            .ok();
+    }
+    terminal.clear().ok();
+
+    match res {
+        Ok(text) => {
+            app.input.set_text(text);
+            app.status = format!("edited in $EDITOR; {}", app.cfg.keys.help(KeyMode::Insert, &[("send", "to send")]));
+        }
+        Err(e) => app.status = format!("editor: {e:#}"),
+    }
+}
+
+fn edit_externally(initial: &str) -> Result<String> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let editor = std::env::var("VISUAL")
+        .ok()
+        .or_else(|| std::env::var("EDITOR").ok())
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    // Allow things like EDITOR="code --wait".
+    let mut parts = editor.split_whitespace();
+    let prog = parts.next().context("empty $EDITOR")?;
+
+    let path = std::env::temp_dir().join(format!("signal-tui-{}-{}.txt", std::process::id(), now_ms()));
+    // The draft may be private: readable by us only.
+    let mut f = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)
+        .with_context(|| format!("create {}", path.display()))?;
+    f.write_all(initial.as_bytes()).context("write temp file")?;
+    drop(f);
+
+    let status = std::process::Command::new(prog).args(parts).arg(&path).status();
+    let text = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+
+    let status = status.with_context(|| format!("run {prog}"))?;
+    if !status.success() {
+        bail!("{prog} exited with {status}; composer unchanged");
+    }
+    let text = text.context("read temp file")?;
+    // Editors add a final newline; it is not part of the message.
+    Ok(text.trim_end_matches('\n').to_string())
+}
+
 struct Args {
     bin: String,
     account: Option<String>,
@@ -283,29 +722,108 @@ fn parse_args() -> Args {
     Args { bin, account, config, help }
 }
 
-fn ingest_incoming(app: &mut App, msgs: Vec<IncomingMessage>) {
-    let selected_key = app.selected_target().map(|t| t.conversation_key.clone());
-    for m in msgs {
-        if !app.targets.iter().any(|t| t.conversation_key == m.conversation_key) {
-            // Add unknown chats on the fly (incoming from unknown numbers / groups).
-            let (kind, addr, display) = if let Some(rest) = m.conversation_key.strip_prefix("group:") {
-                (TargetKind::Group, rest.to_string(), format!("group {rest}"))
-            } else if let Some(rest) = m.conversation_key.strip_prefix("contact:") {
-                let disp = app.cfg.aliases.get(rest).cloned().unwrap_or_else(|| rest.to_string());
-                (TargetKind::Contact, rest.to_string(), disp)
-            } else {
-                continue;
-            };
-            app.targets.push(Target {
-                conversation_key: m.conversation_key.clone(),
-                kind,
-                addr,
-                display,
-            });
+fn ingest_incoming(app: &mut App, idx: usize, rx: Received) {
+    // Only the open chat of the active account counts as "being read".
+    let selected_key = if idx == app.active {
+        app.selected_target().map(|t| t.conversation_key.clone())
+    } else {
+        None
+    };
+    let view = app.chat_view();
+    let acct = &mut app.accounts[idx];
+    let archived = acct.archived.len();
+    for a in rx.identity_alerts {
+        // Verifying works on the open account; other accounts show the
+        // banner once the chat is opened there.
+        if idx == app.active {
+            let who = a.source.as_deref().unwrap_or("unknown");
+            let verify = app.cfg.keys.press(KeyMode::Normal, "verify");
+            app.status = format!("safety number changed for {who}; {verify} to verify");
+        }
+        acct.identity_alerts.insert(a.conversation_key, a.detail);
+    }
+    for u in rx.expiration_updates {
+        let who = u.source.as_deref().unwrap_or("someone");
+        app.status = if u.expires_in_secs == 0 {
+            format!("{who} turned off disappearing messages")
+        } else {
+            format!("{who} set disappearing messages to {}", format_duration(u.expires_in_secs as i64))
+        };
+        if u.expires_in_secs > 0 {
+            forget_history(&mut app.history, &mut app.status, &acct.account, &u.conversation_key);
+        }
+        if let Err(e) = record_timer(&app.cfg, acct, &u.conversation_key, u.expires_in_secs) {
+            app.status = format!("{e:#}");
+        }
+    }
+    if !rx.stories.is_empty() {
+        for st in rx.stories {
+            let dup = acct
+                .stories
+                .iter()
+                .any(|s| s.author == st.author && s.timestamp_ms == st.timestamp_ms);
+            if !dup {
+                acct.stories.push(st);
+            }
+        }
+        // Grouped per author, newest first.
+        acct.stories
+            .sort_by(|a, b| a.author.cmp(&b.author).then(b.timestamp_ms.cmp(&a.timestamp_ms)));
+    }
+    // A call counts as missed once it ends without an answer; ringing for
+    // too long is handled by expire_calls.
+    let mut missed = Vec::new();
+    for u in rx.calls {
+        match u {
+            CallUpdate::Offer(c) => {
+                if acct.seen_calls.insert(c.call_id) {
+                    acct.pending_calls.insert(c.call_id, (now_ms(), c));
+                }
+            }
+            CallUpdate::Answered(id) => {
+                acct.pending_calls.remove(&id);
+            }
+            CallUpdate::Ended(id) => missed.extend(acct.pending_calls.remove(&id).map(|(_, c)| c)),
+        }
+    }
+    for m in rx.messages {
+        if !ensure_target(acct, &app.cfg, &m.conversation_key) {
+            continue;
         }
 
         if selected_key.as_deref() != Some(m.conversation_key.as_str()) {
-            *app.unread.entry(m.conversation_key.clone()).or_insert(0) += 1;
+            *acct.unread.entry(m.conversation_key.clone()).or_insert(0) += 1;
+        }
+
+        // Every message carries the chat's current timer.
+        if m.expires_in_secs > 0 {
+            forget_history(&mut app.history, &mut app.status, &acct.account, &m.conversation_key);
+        }
+        if let Err(e) = record_timer(&app.cfg, acct, &m.conversation_key, m.expires_in_secs) {
+            app.status = format!("{e:#}");
+        }
+        // Like on the phone, the timer starts when the message is read.
+        let read = selected_key.as_deref() == Some(m.conversation_key.as_str());
+        let expires_at_ms = if read { expiry_for(m.expires_in_secs) } else { None };
+
+        let mut body = m.body;
+        if let Some(st) = &m.sticker {
+            body = sticker_label(acct.sticker_packs.as_deref().unwrap_or_default(), st);
+        }
+        let mut view_once = Vec::new();
+        if m.view_once && !m.attachments.is_empty() {
+            let label = if app.cfg.accept_view_once {
+                view_once = m.attachments.iter().filter_map(|a| a.path.clone()).collect();
+                format!("[view-once {}]", media_kind(&m.attachments[0]))
+            } else {
+                for a in &m.attachments {
+                    if let Some(p) = &a.path {
+                        let _ = fs::remove_file(p);
+                    }
+                }
+                "[view-once media refused]".to_string()
+            };
+            body = if body.is_empty() { label } else { format!("{label} {body}") };
         }
 
         if app.cfg.save_scrollback {
@@ -313,273 +831,1815 @@ fn ingest_incoming(app: &mut App, msgs: Vec<IncomingMessage>) {
                 ts_ms: m.timestamp_ms,
                 dir: "in".to_string(),
                 who: m.source.clone(),
-                body: m.body.clone(),
+                body: body.clone(),
+                expires_in_secs: (m.expires_in_secs > 0).then_some(m.expires_in_secs),
+                expires_at_ms,
+                view_once_paths: view_once.iter().map(|p| p.to_string_lossy().into_owned()).collect(),
+                text_styles: m.text_styles.clone(),
+                sticker: m.sticker.clone(),
             };
-            let _ = scrollback::append(&app.cfg.scrollback_dir, &m.conversation_key, &rec);
+            let _ = scrollback::append(
+                &app.cfg.scrollback_dir,
+                &acct.account,
+                &m.conversation_key,
+                &rec,
+            );
         }
 
-        if app.notify_send {
-            notify_incoming(app, &m.conversation_key, m.source.as_deref(), &m.body);
+        let mentioned = m.mentions.iter().any(|id| *id == acct.account || acct.uuid.as_ref() == Some(id));
+        let rule = chat_rule(acct, &app.cfg, &m.conversation_key);
+        if let Some(n) = app.notifier.as_mut()
+            && rule.notifies(now_ms(), mentioned)
+            && let Err(e) = notify_incoming(n, acct, &app.cfg, &m.conversation_key, &rule, m.source.as_deref(), &body)
+        {
+            app.status = format!("{e:#}");
+        }
+        if let Some(h) = &app.hooks
+            && (h.filter() == hooks::HookFilter::All || rule.notifies(now_ms(), mentioned))
+        {
+            let sender_name = m.source.as_deref().map(|n| contact_display(acct, &app.cfg, n));
+            let ev = hooks::MessageEvent {
+                kind: "message",
+                account: &acct.account,
+                conversation: &m.conversation_key,
+                chat_name: chat_name(acct, &m.conversation_key),
+                is_group: m.conversation_key.starts_with("group:"),
+                sender: m.source.as_deref(),
+                sender_name: sender_name.as_deref(),
+                body: &body,
+                timestamp_ms: m.timestamp_ms,
+                mentioned,
+            };
+            if let Err(e) = h.on_message(&ev) {
+                app.status = format!("{e:#}");
+            }
         }
 
-        app.messages
+        touch(acct, &m.conversation_key, m.timestamp_ms);
+        acct.archived.retain(|k| *k != m.conversation_key);
+        acct.messages
             .entry(m.conversation_key.clone())
             .or_default()
             .push(ChatMessage {
                 ts_ms: m.timestamp_ms,
                 dir: MsgDir::In,
                 who: m.source,
-                body: m.body,
+                body,
+                expires_in_secs: m.expires_in_secs,
+                expires_at_ms,
+                view_once,
+                styles: m.text_styles,
+                sticker: m.sticker,
             });
     }
 
-    // Keep list stable but reasonably ordered.
-    app.targets
-        .sort_by(|a, b| a.display.to_lowercase().cmp(&b.display.to_lowercase()));
-    if app.selected >= app.targets.len() && !app.targets.is_empty() {
-        app.selected = app.targets.len() - 1;
+    for c in missed {
+        missed_call(app, idx, selected_key.as_deref(), c);
     }
-}
+    let acct = &mut app.accounts[idx];
 
-fn handle_key(signal: &SignalCli, app: &mut App, k: KeyEvent) -> Result<bool> {
-    if k.code == KeyCode::Char('c') && k.modifiers.contains(KeyModifiers::CONTROL) {
-        return Ok(true);
+    // A new message or call brings an archived chat back.
+    if acct.archived.len() != archived
+        && let Err(e) = state::save_archived(&app.cfg.state_dir, &acct.account, &acct.archived)
+    {
+        app.status = format!("{e:#}");
     }
+    sort_targets(acct, view);
+}
 
-    match app.mode {
-        Mode::Normal => handle_key_normal(signal, app, k),
-        Mode::Insert => handle_key_insert(signal, app, k),
-        Mode::AddRecipient => handle_key_add_recipient(app, k),
+/// Adds a "missed call" line to the caller's chat, notifying like a message.
+fn missed_call(app: &mut App, idx: usize, selected_key: Option<&str>, c: CallEvent) {
+    let acct = &mut app.accounts[idx];
+    if !ensure_target(acct, &app.cfg, &c.conversation_key) {
+        return;
+    }
+    if selected_key != Some(c.conversation_key.as_str()) {
+        *acct.unread.entry(c.conversation_key.clone()).or_insert(0) += 1;
     }
-}
 
-fn handle_key_normal(signal: &SignalCli, app: &mut App, k: KeyEvent) -> Result<bool> {
-    // vim-ish key chords
-    if !matches!(k.code, KeyCode::Char('g')) {
-        app.pending_g = false;
+    let from = c
+        .source
+        .as_deref()
+        .map(|n| contact_display(acct, &app.cfg, n))
+        .unwrap_or_else(|| "unknown".to_string());
+    let body = format!("missed {} call from {from}", if c.video { "video" } else { "voice" });
+
+    if app.cfg.save_scrollback {
+        let rec = ScrollbackRecord {
+            ts_ms: c.timestamp_ms,
+            dir: "system".to_string(),
+            who: c.source.clone(),
+            body: body.clone(),
+            expires_in_secs: None,
+            expires_at_ms: None,
+            view_once_paths: Vec::new(),
+            text_styles: Vec::new(),
+            sticker: None,
+        };
+        let _ = scrollback::append(&app.cfg.scrollback_dir, &acct.account, &c.conversation_key, &rec);
     }
 
-    match k.code {
-        KeyCode::Char('q') => return Ok(true),
-        KeyCode::Char('j') | KeyCode::Down => {
-            if !app.targets.is_empty() {
-                app.selected = (app.selected + 1).min(app.targets.len() - 1);
-                mark_selected_read(app);
-            }
-        }
-        KeyCode::Char('k') | KeyCode::Up => {
-            if !app.targets.is_empty() {
-                app.selected = app.selected.saturating_sub(1);
-                mark_selected_read(app);
-            }
-        }
-        KeyCode::Char('g') => {
-            if app.pending_g {
-                app.selected = 0;
-                app.pending_g = false;
-                mark_selected_read(app);
-            } else {
-                app.pending_g = true;
-            }
+    // A call is meant for us, like a mention.
+    let rule = chat_rule(acct, &app.cfg, &c.conversation_key);
+    if let Some(n) = app.notifier.as_mut()
+        && rule.notifies(now_ms(), true)
+        && let Err(e) = notify_incoming(n, acct, &app.cfg, &c.conversation_key, &rule, c.source.as_deref(), &body)
+    {
+        app.status = format!("{e:#}");
+    }
+    if let Some(h) = &app.hooks
+        && (h.filter() == hooks::HookFilter::All || rule.notifies(now_ms(), true))
+    {
+        let sender_name = c.source.as_deref().map(|n| contact_display(acct, &app.cfg, n));
+        let ev = hooks::MessageEvent {
+            kind: "call",
+            account: &acct.account,
+            conversation: &c.conversation_key,
+            chat_name: chat_name(acct, &c.conversation_key),
+            is_group: c.conversation_key.starts_with("group:"),
+            sender: c.source.as_deref(),
+            sender_name: sender_name.as_deref(),
+            body: &body,
+            timestamp_ms: c.timestamp_ms,
+            mentioned: false,
+        };
+        if let Err(e) = h.on_message(&ev) {
+            app.status = format!("{e:#}");
         }
-        KeyCode::Char('G') => {
-            if !app.targets.is_empty() {
-                app.selected = app.targets.len() - 1;
-                mark_selected_read(app);
-            }
+    }
+
+    touch(acct, &c.conversation_key, c.timestamp_ms);
+    acct.archived.retain(|k| *k != c.conversation_key);
+    acct.messages.entry(c.conversation_key.clone()).or_default().push(ChatMessage {
+        ts_ms: c.timestamp_ms,
+        dir: MsgDir::System,
+        who: c.source,
+        body,
+        expires_in_secs: 0,
+        expires_at_ms: None,
+        view_once: Vec::new(),
+        styles: Vec::new(),
+        sticker: None,
+    });
+}
+
+/// Marks calls that rang for longer than CALL_RING_MS without an answer as missed.
+fn expire_calls(app: &mut App) {
+    let now = now_ms();
+    let view = app.chat_view();
+    for idx in 0..app.accounts.len() {
+        let acct = &mut app.accounts[idx];
+        let expired: Vec<u64> = acct
+            .pending_calls
+            .iter()
+            .filter(|(_, (at, _))| now - at >= CALL_RING_MS)
+            .map(|(&id, _)| id)
+            .collect();
+        if expired.is_empty() {
+            continue;
         }
-        KeyCode::Char('i') => {
-            if app.selected_target().is_some() {
-                app.mode = Mode::Insert;
-                app.input.clear();
-            } else {
-                app.status = "no target selected; press 'a' to add a recipient".to_string();
+        let selected_key = if idx == app.active {
+            app.selected_target().map(|t| t.conversation_key.clone())
+        } else {
+            None
+        };
+        let archived = app.accounts[idx].archived.len();
+        for id in expired {
+            if let Some((_, c)) = app.accounts[idx].pending_calls.remove(&id) {
+                missed_call(app, idx, selected_key.as_deref(), c);
             }
         }
-        KeyCode::Char('a') => {
-            app.mode = Mode::AddRecipient;
-            app.input.clear();
-            app.status = "add recipient: type E.164 number like +15551234567, Enter to add, Esc to cancel".to_string();
-        }
-        KeyCode::Char('r') => {
-            match signal.receive_once(&app.account, 1) {
-                Ok(msgs) => {
-                    if msgs.is_empty() {
-                        app.status = "sync: no new messages".to_string();
-                    } else {
-                        app.status = format!("sync: received {} message(s)", msgs.len());
-                        ingest_incoming(app, msgs);
-                        app.title_dirty = true;
-                    }
-                }
-                Err(e) => app.status = format!("sync error: {e:#}"),
-            }
+        let acct = &mut app.accounts[idx];
+        if acct.archived.len() != archived
+            && let Err(e) = state::save_archived(&app.cfg.state_dir, &acct.account, &acct.archived)
+        {
+            app.status = format!("{e:#}");
         }
-        _ => {}
+        sort_targets(acct, view);
+        app.title_dirty = true;
     }
-    Ok(false)
 }
 
-fn handle_key_insert(signal: &SignalCli, app: &mut App, k: KeyEvent) -> Result<bool> {
-    match k.code {
-        KeyCode::Esc => {
-            app.mode = Mode::Normal;
-            app.input.clear();
-        }
-        KeyCode::Enter => {
-            let body = app.input.trim().to_string();
-            if body.is_empty() {
-                app.status = "empty message; nothing sent".to_string();
-                return Ok(false);
-            }
-            let Some(t) = app.selected_target().cloned() else {
-                app.status = "no target selected".to_string();
-                return Ok(false);
-            };
+// How long a call rings before it counts as missed.
+const CALL_RING_MS: i64 = 60 * 1000;
+
+/// Notification settings of a chat: changes made in the TUI over `[chats]`.
+fn chat_rule(acct: &AccountState, cfg: &config::Config, conversation_key: &str) -> ChatRule {
+    let addr = conversation_key.split_once(':').map(|(_, a)| a).unwrap_or(conversation_key);
+    acct.notify_rules
+        .get(conversation_key)
+        .cloned()
+        .unwrap_or_default()
+        .or(cfg.chat_rules.get(addr))
+}
 
-            let send_res = match t.kind {
-                TargetKind::Contact => signal.send_message_to_number(&app.account, &t.addr, &body),
-                TargetKind::Group => signal.send_message_to_group(&app.account, &t.addr, &body),
-            };
-            match send_res {
-                Ok(()) => {
-                    if app.cfg.save_scrollback {
-                        let rec = ScrollbackRecord {
-                            ts_ms: None,
-                            dir: "out".to_string(),
-                            who: Some(app.account.clone()),
-                            body: body.clone(),
-                        };
-                        let _ = scrollback::append(&app.cfg.scrollback_dir, &t.conversation_key, &rec);
-                    }
-                    app.messages.entry(t.conversation_key.clone()).or_default().push(ChatMessage {
-                        ts_ms: None,
-                        dir: MsgDir::Out,
-                        who: Some(app.account.clone()),
-                        body: body.clone(),
-                    });
-                    app.status = "sent".to_string();
-                    app.input.clear();
-                    app.mode = Mode::Normal;
-                }
-                Err(e) => app.status = format!("send error: {e:#}"),
-            }
-        }
-        KeyCode::Backspace => {
-            app.input.pop();
-        }
-        KeyCode::Char(c) => {
-            if !k.modifiers.contains(KeyModifiers::CONTROL) && !k.modifiers.contains(KeyModifiers::ALT) {
-                app.input.push(c);
-            }
-        }
-        _ => {}
+/// Changes the stored notification settings of the selected chat.
+fn update_chat_rule(app: &mut App, change: impl FnOnce(&mut ChatRule)) -> Result<()> {
+    let key = app.selected_target().map(|t| t.conversation_key.clone()).unwrap_or_default();
+    let acct = &mut app.accounts[app.active];
+    let rule = acct.notify_rules.entry(key.clone()).or_default();
+    change(rule);
+    if rule.is_empty() {
+        acct.notify_rules.remove(&key);
     }
-    Ok(false)
+    state::save_notify_rules(&app.cfg.state_dir, &acct.account, &acct.notify_rules)
 }
 
-fn handle_key_add_recipient(app: &mut App, k: KeyEvent) -> Result<bool> {
-    match k.code {
-        KeyCode::Esc => {
-            app.mode = Mode::Normal;
-            app.input.clear();
-            app.status = "cancelled".to_string();
-        }
-        KeyCode::Enter => {
-            let num = app.input.trim().to_string();
-            if !num.starts_with('+') || num.len() < 8 {
-                app.status = "recipient must look like +15551234567".to_string();
-                return Ok(false);
-            }
-            let key = format!("contact:{num}");
-            if !app.targets.iter().any(|t| t.conversation_key == key) {
-                let display = app
-                    .cfg
-                    .aliases
-                    .get(&num)
-                    .cloned()
-                    .unwrap_or_else(|| num.clone());
-                app.targets.push(Target {
-                    conversation_key: key,
-                    kind: TargetKind::Contact,
-                    addr: num.clone(),
-                    display,
-                });
-                app.targets
-                    .sort_by(|a, b| a.display.to_lowercase().cmp(&b.display.to_lowercase()));
-            }
-            if let Some(i) = app.targets.iter().position(|t| t.addr == num) {
-                app.selected = i;
+/// Drops `:mute <duration>` mutes that ran out, so the chat's unread count is
+/// back in the window title.
+fn expire_mutes(app: &mut App) {
+    let now = now_ms();
+    for acct in &mut app.accounts {
+        let mut changed = false;
+        for rule in acct.notify_rules.values_mut() {
+            if let Some(Mute::Until(at)) = rule.mute
+                && at <= now
+            {
+                rule.mute = None;
+                changed = true;
             }
-            mark_selected_read(app);
-            app.mode = Mode::Normal;
-            app.input.clear();
-            app.status = "recipient added (press 'i' to message)".to_string();
         }
-        KeyCode::Backspace => {
-            app.input.pop();
+        if !changed {
+            continue;
         }
-        KeyCode::Char(c) => {
-            if !k.modifiers.contains(KeyModifiers::CONTROL) && !k.modifiers.contains(KeyModifiers::ALT) {
-                app.input.push(c);
-            }
+        acct.notify_rules.retain(|_, r| !r.is_empty());
+        if let Err(e) = state::save_notify_rules(&app.cfg.state_dir, &acct.account, &acct.notify_rules) {
+            app.status = format!("{e:#}");
         }
-        _ => {}
+        app.title_dirty = true;
     }
-    Ok(false)
 }
 
-fn ui(f: &mut Frame, app: &App) {
-    let root = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Min(1), Constraint::Length(4)])
-        .split(f.area());
+/// Records activity in a chat now (or at `ts_ms`, whichever is later).
+fn touch(acct: &mut AccountState, conversation_key: &str, ts_ms: Option<i64>) {
+    let ts = ts_ms.unwrap_or_else(now_ms);
+    let last = acct.last_activity.entry(conversation_key.to_string()).or_default();
+    *last = (*last).max(ts);
+}
 
-    let main = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
-        .split(root[0]);
+/// Orders the chat list: listed chats before filtered-out ones, pinned chats
+/// first, then by `view.sort`, then by name. Without `view.all` only chats
+/// with messages (or pinned, with a draft, or opened by name) are listed;
+/// archived ones only in the archived view. The selection follows the chat
+/// it was on, not the row.
+fn sort_targets(acct: &mut AccountState, view: ChatView) {
+    let selected = acct.selected_target().map(|t| t.conversation_key.clone());
+    let pinned = |t: &Target| acct.pinned.contains(&t.conversation_key);
+    let last = |t: &Target| acct.last_activity.get(&t.conversation_key).copied().unwrap_or(0);
+    let unread = |t: &Target| acct.unread.get(&t.conversation_key).is_some_and(|&n| n > 0);
+    let listed = |t: &Target| {
+        let key = &t.conversation_key;
+        let archived = acct.archived.contains(key);
+        if view.archived {
+            return archived;
+        }
+        let active = acct.last_activity.contains_key(key) || acct.messages.get(key).is_some_and(|m| !m.is_empty());
+        acct.revealed.contains(key) || (!archived && (view.all || active || pinned(t) || acct.drafts.contains_key(key)))
+    };
+    acct.targets.sort_by(|a, b| {
+        let by_sort = match view.sort {
+            config::ChatSort::Recent => last(b).cmp(&last(a)),
+            config::ChatSort::Unread => unread(b).cmp(&unread(a)).then_with(|| last(b).cmp(&last(a))),
+            config::ChatSort::Alpha => std::cmp::Ordering::Equal,
+        };
+        listed(b)
+            .cmp(&listed(a))
+            .then(pinned(b).cmp(&pinned(a)))
+            .then(by_sort)
+            .then_with(|| a.display.to_lowercase().cmp(&b.display.to_lowercase()))
+    });
+    acct.shown = acct.targets.iter().filter(|t| listed(t)).count();
+    acct.selected = selected
+        .and_then(|k| acct.targets.iter().position(|t| t.conversation_key == k))
+        .unwrap_or(acct.selected)
+        .min(acct.shown.saturating_sub(1));
+}
 
-    draw_targets(f, app, main[0]);
-    draw_chat(f, app, main[1]);
-    draw_status(f, app, root[1]);
+/// Selects the chat with `conversation_key`, listing it for the rest of the
+/// session if the current filter hides it.
+fn select_chat(app: &mut App, conversation_key: &str) {
+    let view = app.chat_view();
+    let acct = app.acct_mut();
+    let Some(i) = acct.targets.iter().position(|t| t.conversation_key == conversation_key) else { return; };
+    if i >= acct.shown {
+        acct.revealed.insert(conversation_key.to_string());
+        sort_targets(acct, view);
+    }
+    if let Some(i) = acct.targets.iter().position(|t| t.conversation_key == conversation_key) {
+        acct.selected = i;
+    }
+    mark_selected_read(app);
 }
 
-fn draw_targets(f: &mut Frame, app: &App, area: Rect) {
-    let items: Vec<ListItem> = app
-        .targets
-        .iter()
-        .enumerate()
-        .map(|(i, t)| {
-            let unread = *app.unread.get(&t.conversation_key).unwrap_or(&0);
-            let mut style = Style::default();
-            if i == app.selected {
-                style = style
-                    .fg(Color::Black)
-                    .bg(Color::LightGreen)
-                    .add_modifier(Modifier::BOLD);
+/// Adds unknown chats on the fly (incoming from unknown numbers / groups).
+/// Returns false if the key is not something we can chat with.
+fn ensure_target(acct: &mut AccountState, cfg: &config::Config, conversation_key: &str) -> bool {
+    if acct.targets.iter().any(|t| t.conversation_key == conversation_key) {
+        return true;
+    }
+    let (kind, addr, display) = if let Some(rest) = conversation_key.strip_prefix("group:") {
+        (TargetKind::Group, rest.to_string(), format!("group {rest}"))
+    } else if let Some(rest) = conversation_key.strip_prefix("contact:") {
+        let disp = cfg.aliases.get(rest).cloned().unwrap_or_else(|| rest.to_string());
+        (TargetKind::Contact, rest.to_string(), disp)
+    } else {
+        return false;
+    };
+    acct.targets.push(Target {
+        conversation_key: conversation_key.to_string(),
+        kind,
+        addr,
+        display,
+        name: None,
+    });
+    true
+}
+
+fn handle_key(signal: &SignalCli, app: &mut App, k: KeyEvent) -> Result<bool> {
+    if k.code == KeyCode::Char('c') && k.modifiers.contains(KeyModifiers::CONTROL) {
+        return Ok(true);
+    }
+
+    if app.history_search.is_some() && handle_history_search(app, k) {
+        return Ok(false);
+    }
+
+    let mode = app.mode;
+    let res = match app.mode {
+        Mode::Normal => handle_key_normal(signal, app, k),
+        Mode::Insert => handle_key_insert(signal, app, k),
+        Mode::AddRecipient => handle_key_add_recipient(app, k),
+        Mode::Identity => handle_key_identity(signal, app, k),
+        Mode::AccountPicker => handle_key_account_picker(app, k),
+        Mode::SetTimer => handle_key_set_timer(signal, app, k),
+        Mode::Command => handle_key_command(signal, app, k),
+        Mode::StickerPicker => handle_key_sticker_picker(signal, app, k),
+        Mode::Stories => handle_key_stories(app, k),
+        Mode::Finder => handle_key_finder(app, k),
+    };
+    // Recall state and half-typed key sequences belong to one mode.
+    if app.mode != mode {
+        app.history_nav = None;
+        app.history_search = None;
+        app.pending_keys.clear();
+    }
+    res
+}
+
+/// The history the current prompt recalls from, if it has one.
+fn history_list(app: &App) -> Option<HistoryList> {
+    match app.mode {
+        Mode::Insert => app
+            .selected_target()
+            .map(|t| HistoryList::Messages(app.acct().account.clone(), t.conversation_key.clone())),
+        Mode::AddRecipient | Mode::SetTimer | Mode::Command => Some(HistoryList::Commands),
+        _ => None,
+    }
+}
+
+fn start_history_search(app: &mut App) {
+    app.history_search = Some(HistorySearch {
+        query: String::new(),
+        hit: None,
+        stash: app.input.text().to_string(),
+    });
+}
+
+fn remember_input(app: &mut App, entry: &str) {
+    let Some(list) = history_list(app) else { return; };
+    // Disappearing messages should not live on in the history either.
+    if let HistoryList::Messages(_, key) = &list
+        && disappearing(app.acct(), key)
+    {
+        return;
+    }
+    if let Err(e) = app.history.push(&list, entry) {
+        app.status = format!("save history: {e:#}");
+    }
+}
+
+fn disappearing(acct: &AccountState, key: &str) -> bool {
+    acct.timers.get(key).is_some_and(|&t| t > 0)
+}
+
+/// Whether what was typed in a chat (sent messages, drafts) may be written to
+/// the state dir: only with `save_scrollback`, and never for disappearing chats.
+fn keeps_on_disk(cfg: &config::Config, acct: &AccountState, key: &str) -> bool {
+    cfg.save_scrollback && !disappearing(acct, key)
+}
+
+/// Drops a chat's sent-message history once it has a disappearing-message timer.
+fn forget_history(history: &mut History, status: &mut String, account: &str, conversation_key: &str) {
+    if let Err(e) = history.forget(account, conversation_key) {
+        *status = format!("save history: {e:#}");
+    }
+}
+
+/// Passes `k` to the line editor; Up/Down it does not use step through history.
+fn edit_or_recall(app: &mut App, k: KeyEvent, multiline: bool) {
+    if app.input.handle_key(k, multiline) {
+        return;
+    }
+    let older = match k.code {
+        KeyCode::Up => true,
+        KeyCode::Down => false,
+        _ => return,
+    };
+    let Some(list) = history_list(app) else { return; };
+    let entries = app.history.entries(&list);
+    let next = match (&app.history_nav, older) {
+        (None, true) if !entries.is_empty() => Some(entries.len() - 1),
+        (None, _) => return,
+        (Some((pos, _)), true) => Some(pos.saturating_sub(1)),
+        (Some((pos, _)), false) => Some(pos + 1).filter(|&p| p < entries.len()),
+    };
+    match next {
+        Some(pos) => {
+            let text = entries[pos].clone();
+            let stash = match app.history_nav.take() {
+                Some((_, stash)) => stash,
+                None => app.input.text().to_string(),
+            };
+            app.history_nav = Some((pos, stash));
+            app.input.set_text(text);
+        }
+        // Past the newest entry: back to what was being typed.
+        None => {
+            if let Some((_, stash)) = app.history_nav.take() {
+                app.input.set_text(stash);
+            }
+        }
+    }
+}
+
+/// Ctrl-r search keys. Returns false if the key ends the search and should be
+/// handled as usual (the match stays in the composer).
+fn handle_history_search(app: &mut App, k: KeyEvent) -> bool {
+    let Some(list) = history_list(app) else { return false; };
+    let Some(search) = app.history_search.as_mut() else { return false; };
+    let ctrl = k.modifiers.contains(KeyModifiers::CONTROL);
+    let before = match k.code {
+        KeyCode::Char('r') if ctrl => search.hit.unwrap_or(usize::MAX),
+        KeyCode::Char('g') if ctrl => {
+            let stash = std::mem::take(&mut search.stash);
+            app.input.set_text(stash);
+            app.history_search = None;
+            return true;
+        }
+        KeyCode::Esc => {
+            let stash = std::mem::take(&mut search.stash);
+            app.input.set_text(stash);
+            app.history_search = None;
+            return true;
+        }
+        KeyCode::Enter => {
+            app.history_search = None;
+            return true;
+        }
+        KeyCode::Backspace => {
+            search.query.pop();
+            usize::MAX
+        }
+        KeyCode::Char(c) if !ctrl => {
+            search.query.push(c);
+            usize::MAX
+        }
+        _ => {
+            app.history_search = None;
+            return false;
+        }
+    };
+    match app.history.search(&list, &search.query, before) {
+        Some(i) => {
+            search.hit = Some(i);
+            app.input.set_text(app.history.entries(&list)[i].clone());
+        }
+        None => app.status = format!("no match for '{}'", search.query),
+    }
+    true
+}
+
+fn handle_key_normal(signal: &SignalCli, app: &mut App, k: KeyEvent) -> Result<bool> {
+    // Every binding is a `:` command line, or opens the command line.
+    let fed = app.cfg.keys.feed(KeyMode::Normal, &mut app.pending_keys, k);
+    match fed.action.as_deref() {
+        Some("command-line") => {
+            app.mode = Mode::Command;
+            app.input.clear();
+            Ok(false)
+        }
+        Some(line) => Ok(dispatch_command(signal, app, line)),
+        None => Ok(false),
+    }
+}
+
+fn handle_key_command(signal: &SignalCli, app: &mut App, k: KeyEvent) -> Result<bool> {
+    let fed = app.cfg.keys.feed(KeyMode::Prompt, &mut app.pending_keys, k);
+    for k in fed.unbound {
+        edit_or_recall(app, k, false);
+    }
+    match fed.action.as_deref() {
+        Some("cancel") => {
+            app.mode = Mode::Normal;
+            app.input.clear();
+        }
+        Some("accept") => {
+            let line = app.input.text().trim().to_string();
+            remember_input(app, &line);
+            app.mode = Mode::Normal;
+            app.input.clear();
+            return Ok(dispatch_command(signal, app, &line));
+        }
+        Some("complete") => complete_command(app),
+        Some("history-search") => start_history_search(app),
+        _ => {}
+    }
+    Ok(false)
+}
+
+/// Runs a command line, reporting errors in the status line. Returns true to quit.
+fn dispatch_command(signal: &SignalCli, app: &mut App, line: &str) -> bool {
+    match run_command(signal, app, line) {
+        Ok(quit) => quit,
+        Err(e) => {
+            app.status = format!("{e:#}");
+            false
+        }
+    }
+}
+
+fn run_command(signal: &SignalCli, app: &mut App, line: &str) -> Result<bool> {
+    let (name, args) = commands::split(line);
+    if name.is_empty() {
+        return Ok(false);
+    }
+    let cmd = commands::find(name).map_err(anyhow::Error::msg)?;
+
+    let has_target = app.selected_target().is_some();
+    match cmd.id {
+        Cmd::Quit => return Ok(true),
+        Cmd::Down if app.acct().shown > 0 => {
+            let acct = app.acct_mut();
+            acct.selected = (acct.selected + 1).min(acct.shown - 1);
+            mark_selected_read(app);
+        }
+        Cmd::Up if app.acct().shown > 0 => {
+            let acct = app.acct_mut();
+            acct.selected = acct.selected.saturating_sub(1);
+            mark_selected_read(app);
+        }
+        Cmd::Top if app.acct().shown > 0 => {
+            app.acct_mut().selected = 0;
+            mark_selected_read(app);
+        }
+        Cmd::Bottom if app.acct().shown > 0 => {
+            let acct = app.acct_mut();
+            acct.selected = acct.shown - 1;
+            mark_selected_read(app);
+        }
+        Cmd::Down | Cmd::Up | Cmd::Top | Cmd::Bottom => {}
+        Cmd::Open => open_chat(app, args)?,
+        Cmd::Insert
+        | Cmd::Edit
+        | Cmd::Send
+        | Cmd::Timer
+        | Cmd::Mute
+        | Cmd::Mentions
+        | Cmd::NotifyTitle
+        | Cmd::Pin
+        | Cmd::Archive
+        | Cmd::Search
+        | Cmd::Spoilers
+            if !has_target =>
+        {
+            bail!("no target selected; {} to add a recipient", app.cfg.keys.press(KeyMode::Normal, "add"));
+        }
+        Cmd::Insert => {
+            app.mode = Mode::Insert;
+            restore_draft(app);
+        }
+        Cmd::Edit => {
+            app.mode = Mode::Insert;
+            restore_draft(app);
+            app.external_edit = true;
+        }
+        Cmd::Send => {
+            if send_text(signal, app, args) {
+                app.status = "sent".to_string();
+            }
+        }
+        Cmd::Add if args.is_empty() => {
+            app.mode = Mode::AddRecipient;
+            app.input.clear();
+            app.status = format!(
+                "add recipient: type E.164 number like +15551234567, {}",
+                app.cfg.keys.help(KeyMode::Prompt, &[("accept", "to add"), ("cancel", "to cancel")])
+            );
+        }
+        Cmd::Add => add_recipient(app, args)?,
+        Cmd::Alias => set_alias(app, args)?,
+        Cmd::Search => search_chat(app, args),
+        Cmd::Find => {
+            app.mode = Mode::Finder;
+            app.input.set_text(args);
+            update_finder(app);
+        }
+        Cmd::Pin => {
+            let key = app.selected_target().map(|t| t.conversation_key.clone()).unwrap_or_default();
+            let view = app.chat_view();
+            let acct = &mut app.accounts[app.active];
+            if let Some(i) = acct.pinned.iter().position(|k| *k == key) {
+                acct.pinned.remove(i);
+                app.status = "unpinned".to_string();
+            } else {
+                acct.pinned.push(key);
+                app.status = "pinned to the top".to_string();
+            }
+            state::save_pinned(&app.cfg.state_dir, &acct.account, &acct.pinned)?;
+            sort_targets(acct, view);
+        }
+        Cmd::Archive => {
+            let key = app.selected_target().map(|t| t.conversation_key.clone()).unwrap_or_default();
+            let view = app.chat_view();
+            let acct = &mut app.accounts[app.active];
+            if let Some(i) = acct.archived.iter().position(|k| *k == key) {
+                acct.archived.remove(i);
+                app.status = "unarchived".to_string();
+            } else {
+                acct.archived.push(key.clone());
+                app.status = "archived; it comes back with the next message".to_string();
+            }
+            acct.revealed.remove(&key);
+            state::save_archived(&app.cfg.state_dir, &acct.account, &acct.archived)?;
+            sort_targets(acct, view);
+        }
+        Cmd::Archived => {
+            app.show_archived = !app.show_archived;
+            let view = app.chat_view();
+            for acct in &mut app.accounts {
+                sort_targets(acct, view);
+            }
+            app.status = if app.show_archived {
+                format!("archived chats ({})", app.acct().archived.len())
+            } else {
+                "chats".to_string()
+            };
+        }
+        Cmd::All => {
+            app.show_all = !app.show_all;
+            let view = app.chat_view();
+            for acct in &mut app.accounts {
+                sort_targets(acct, view);
+            }
+            app.status = if app.show_all {
+                "showing every known recipient".to_string()
+            } else {
+                "showing chats with messages".to_string()
+            };
+        }
+        Cmd::Sort if args.is_empty() => {
+            app.status = format!("sorted by {} (:sort {})", app.cfg.sort.name(), config::ChatSort::NAMES.join("|"));
+        }
+        Cmd::Sort => {
+            app.cfg.sort = config::ChatSort::parse(args)
+                .with_context(|| format!("usage: {}", commands::usage(cmd)))?;
+            let view = app.chat_view();
+            for acct in &mut app.accounts {
+                sort_targets(acct, view);
+            }
+            app.status = format!("sorted by {}", view.sort.name());
+        }
+        Cmd::Mute => {
+            let key = app.selected_target().map(|t| t.conversation_key.clone()).unwrap_or_default();
+            let now = now_ms();
+            let mute = match args {
+                "" if chat_rule(app.acct(), &app.cfg, &key).muted(now) => Mute::Off,
+                "" | "on" => Mute::Forever,
+                _ => match parse_timer(args) {
+                    Some(0) => Mute::Off,
+                    Some(secs) => Mute::Until(now + i64::from(secs) * 1000),
+                    None => bail!("usage: {}", commands::usage(cmd)),
+                },
+            };
+            update_chat_rule(app, |r| r.mute = Some(mute))?;
+            app.status = match mute {
+                Mute::Off => "notifications on for this chat".to_string(),
+                Mute::Forever => "notifications muted for this chat".to_string(),
+                Mute::Until(at) => format!("notifications muted for {}", format_duration((at - now) / 1000)),
+            };
+            app.title_dirty = true;
+        }
+        Cmd::Mentions => {
+            let key = app.selected_target().map(|t| t.conversation_key.clone()).unwrap_or_default();
+            let on = match args {
+                "" => !chat_rule(app.acct(), &app.cfg, &key).mentions_only.unwrap_or(false),
+                "on" => true,
+                "off" => false,
+                _ => bail!("usage: {}", commands::usage(cmd)),
+            };
+            update_chat_rule(app, |r| r.mentions_only = Some(on))?;
+            app.status = if on {
+                "notifications only when you are mentioned".to_string()
+            } else {
+                "notifications for every message".to_string()
+            };
+        }
+        Cmd::NotifyTitle => {
+            let title = (!args.is_empty()).then(|| args.to_string());
+            update_chat_rule(app, |r| r.title = title)?;
+            app.status = if args.is_empty() {
+                "default notification title".to_string()
+            } else {
+                format!("notifications titled \"{args}\"")
+            };
+        }
+        Cmd::Verify => open_identity_view(signal, app),
+        Cmd::Trust => trust_identities(signal, app)?,
+        Cmd::ViewOnce => open_view_once(app),
+        Cmd::Sticker => open_sticker_picker(signal, app),
+        Cmd::Stories => {
+            if app.acct().stories.is_empty() {
+                app.status = "no stories".to_string();
+            } else {
+                app.story_cursor = 0;
+                app.mode = Mode::Stories;
+            }
+        }
+        Cmd::ReplyStory => reply_to_story(app)?,
+        Cmd::Spoilers => {
+            if let Some(key) = app.selected_target().map(|t| t.conversation_key.clone())
+                && !app.revealed_spoilers.remove(&key)
+            {
+                app.revealed_spoilers.insert(key);
+            }
+        }
+        Cmd::Timer if args.is_empty() => {
+            app.mode = Mode::SetTimer;
+            app.input.clear();
+            app.status = "disappearing messages: e.g. 30s, 5m, 8h, 1d, 4w or off".to_string();
+        }
+        Cmd::Timer => set_timer(signal, app, args)?,
+        Cmd::Account if app.accounts.len() < 2 => {
+            app.status = format!("only one account ({})", app.acct().account);
+        }
+        Cmd::Account if args.is_empty() => {
+            app.account_cursor = app.active;
+            app.mode = Mode::AccountPicker;
+        }
+        Cmd::Account => {
+            let idx = match args.parse::<usize>() {
+                Ok(n) if (1..=app.accounts.len()).contains(&n) => n - 1,
+                Ok(_) => bail!("no account {args} (1-{})", app.accounts.len()),
+                Err(_) => app
+                    .accounts
+                    .iter()
+                    .position(|a| a.account == args)
+                    .with_context(|| format!("no account {args}"))?,
+            };
+            switch_account(app, idx);
+        }
+        Cmd::Sync => match signal.receive_once(&app.acct().account, 1) {
+            Ok(rx) => {
+                if rx.is_empty() {
+                    app.status = "sync: no new messages".to_string();
+                } else {
+                    app.status = format!("sync: received {} message(s)", rx.messages.len());
+                    let idx = app.active;
+                    load_sticker_packs_for(signal, &mut app.accounts[idx], &rx);
+                    ingest_incoming(app, idx, rx);
+                    app.title_dirty = true;
+                }
+            }
+            Err(e) => bail!("sync error: {e:#}"),
+        },
+        Cmd::Help if args.is_empty() => {
+            let names: Vec<&str> = commands::COMMANDS.iter().map(|c| c.name).collect();
+            app.status = format!("commands: {}  (:help <command> for details)", names.join(" "));
+        }
+        Cmd::Help => {
+            let c = commands::find(args).map_err(anyhow::Error::msg)?;
+            app.status = commands::usage(c);
+        }
+    }
+    Ok(false)
+}
+
+/// Tab in command mode: completes the command name, or its argument for
+/// commands that take a chat, account or command name.
+fn complete_command(app: &mut App) {
+    let text = app.input.text().to_string();
+    let candidates: Vec<String> = match text.split_once(' ') {
+        None => commands::COMMANDS
+            .iter()
+            .filter(|c| c.name.starts_with(text.as_str()))
+            .map(|c| format!("{} ", c.name))
+            .collect(),
+        Some((name, arg)) => {
+            let Ok(cmd) = commands::find(name) else { return; };
+            let pool: Vec<String> = match cmd.name {
+                "open" => app.acct().targets.iter().map(|t| t.display.clone()).collect(),
+                "alias" if !arg.contains(' ') => app
+                    .acct()
+                    .targets
+                    .iter()
+                    .filter(|t| t.kind == TargetKind::Contact)
+                    .map(|t| t.addr.clone())
+                    .collect(),
+                "account" => app.accounts.iter().map(|a| a.account.clone()).collect(),
+                "help" => commands::COMMANDS.iter().map(|c| c.name.to_string()).collect(),
+                "mute" | "mentions" => vec!["on".to_string(), "off".to_string()],
+                "sort" => config::ChatSort::NAMES.iter().map(|s| s.to_string()).collect(),
+                _ => Vec::new(),
+            };
+            let lower = arg.to_lowercase();
+            pool.into_iter()
+                .filter(|c| c.to_lowercase().starts_with(&lower))
+                .map(|c| format!("{name} {c}"))
+                .collect()
+        }
+    };
+
+    match candidates.as_slice() {
+        [] => app.status = "no completions".to_string(),
+        [only] => app.input.set_text(only.clone()),
+        _ => {
+            let prefix = commands::common_prefix(&candidates);
+            if prefix.len() > text.len() {
+                app.input.set_text(prefix);
+            } else {
+                let shown: Vec<&str> = candidates
+                    .iter()
+                    .map(|c| c.rsplit_once(' ').map(|(_, last)| last).filter(|l| !l.is_empty()).unwrap_or(c.trim()))
+                    .collect();
+                app.status = shown.join("  ");
+            }
+        }
+    }
+}
+
+/// Inline help for the command being typed.
+fn command_hint(app: &App) -> String {
+    let (name, _) = commands::split(app.input.text());
+    if name.is_empty() {
+        let keys = app.cfg.keys.help(KeyMode::Prompt, &[("complete", "complete"), ("accept", "run"), ("cancel", "cancel")]);
+        return format!("command: {keys}, :help lists commands");
+    }
+    match commands::find(name) {
+        Ok(c) => commands::usage(c),
+        Err(e) => e,
+    }
+}
+
+/// Selects the chat whose name or number matches `query` (exact first, then substring).
+fn open_chat(app: &mut App, query: &str) -> Result<()> {
+    if query.is_empty() {
+        bail!("usage: :open <chat>");
+    }
+    let q = query.to_lowercase();
+    let targets = &app.acct().targets;
+    let exact = targets
+        .iter()
+        .position(|t| t.display.to_lowercase() == q || t.addr.to_lowercase() == q);
+    let idx = match exact {
+        Some(i) => i,
+        None => {
+            let hits: Vec<usize> = (0..targets.len())
+                .filter(|&i| {
+                    targets[i].display.to_lowercase().contains(&q) || targets[i].addr.to_lowercase().contains(&q)
+                })
+                .collect();
+            match hits.as_slice() {
+                [i] => *i,
+                [] => bail!("no chat matches {query}"),
+                _ => bail!("{} chats match {query}; be more specific", hits.len()),
+            }
+        }
+    };
+    let key = targets[idx].conversation_key.clone();
+    select_chat(app, &key);
+    Ok(())
+}
+
+/// Ranks the chat list against the finder query: best fuzzy score over
+/// name, alias, number and group name, then most recent activity.
+fn update_finder(app: &mut App) {
+    let query = app.input.text();
+    let acct = &app.accounts[app.active];
+    let mut hits: Vec<(i64, i64, &Target)> = acct
+        .targets
+        .iter()
+        .filter_map(|t| {
+            let alias = app.cfg.aliases.get(&t.addr);
+            let score = [Some(&t.display), t.name.as_ref(), alias, Some(&t.addr)]
+                .into_iter()
+                .flatten()
+                .filter_map(|field| fuzzy::score(query, field))
+                .max()?;
+            let last = acct.last_activity.get(&t.conversation_key).copied().unwrap_or(0);
+            Some((score, last, t))
+        })
+        .collect();
+    hits.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then(b.1.cmp(&a.1))
+            .then_with(|| a.2.display.to_lowercase().cmp(&b.2.display.to_lowercase()))
+    });
+    app.finder_matches = hits.into_iter().map(|(_, _, t)| t.conversation_key.clone()).collect();
+    app.finder_cursor = 0;
+}
+
+fn handle_key_finder(app: &mut App, k: KeyEvent) -> Result<bool> {
+    let fed = app.cfg.keys.feed(KeyMode::Finder, &mut app.pending_keys, k);
+    for k in fed.unbound {
+        let before = app.input.text().to_string();
+        app.input.handle_key(k, false);
+        if app.input.text() != before {
+            update_finder(app);
+        }
+    }
+    match fed.action.as_deref() {
+        Some("down") => app.finder_cursor += 1,
+        Some("up") => app.finder_cursor = app.finder_cursor.saturating_sub(1),
+        Some("cancel") => {
+            app.mode = Mode::Normal;
+            app.input.clear();
+        }
+        Some("accept") => match app.finder_matches.get(app.finder_cursor).cloned() {
+            Some(key) => {
+                app.mode = Mode::Normal;
+                app.input.clear();
+                select_chat(app, &key);
+            }
+            None => app.status = "no chat matches".to_string(),
+        },
+        _ => {}
+    }
+    app.finder_cursor = app.finder_cursor.min(app.finder_matches.len().saturating_sub(1));
+    Ok(false)
+}
+
+/// Shows `+E164` under a name for the rest of the session (all accounts).
+fn set_alias(app: &mut App, args: &str) -> Result<()> {
+    let Some((num, name)) = args.split_once(char::is_whitespace) else {
+        bail!("usage: :alias <+E164> <name...>");
+    };
+    let name = name.trim();
+    if !num.starts_with('+') || name.is_empty() {
+        bail!("usage: :alias <+E164> <name...>");
+    }
+    app.cfg.aliases.insert(num.to_string(), name.to_string());
+    let view = app.chat_view();
+    for acct in &mut app.accounts {
+        for t in acct.targets.iter_mut().filter(|t| t.kind == TargetKind::Contact && t.addr == num) {
+            t.display = name.to_string();
+        }
+        sort_targets(acct, view);
+    }
+    app.status = format!("{num} is now shown as {name} (this session; add it to [aliases] in config.toml to keep it)");
+    Ok(())
+}
+
+/// Highlights messages in the selected chat that contain `query`; empty clears.
+fn search_chat(app: &mut App, query: &str) {
+    if query.is_empty() {
+        app.chat_search = None;
+        app.status = "search cleared".to_string();
+        return;
+    }
+    let q = query.to_lowercase();
+    let hits = app
+        .selected_target()
+        .and_then(|t| app.acct().messages.get(&t.conversation_key))
+        .map(|msgs| msgs.iter().filter(|m| m.body.to_lowercase().contains(&q)).count())
+        .unwrap_or(0);
+    app.status = format!("{hits} message(s) match '{query}' (:search to clear)");
+    app.chat_search = Some(q);
+}
+
+fn switch_account(app: &mut App, idx: usize) {
+    app.active = idx;
+    app.mode = Mode::Normal;
+    app.status = format!("using account {}", app.acct().account);
+    mark_selected_read(app);
+    app.title_dirty = true;
+}
+
+fn handle_key_insert(signal: &SignalCli, app: &mut App, k: KeyEvent) -> Result<bool> {
+    let fed = app.cfg.keys.feed(KeyMode::Insert, &mut app.pending_keys, k);
+    for k in fed.unbound {
+        edit_or_recall(app, k, true);
+    }
+    match fed.action.as_deref() {
+        Some("cancel") => {
+            stash_draft(app);
+            app.mode = Mode::Normal;
+            app.input.clear();
+            app.story_reply = None;
+        }
+        Some("newline") => app.input.insert_str("\n"),
+        Some("edit") => app.external_edit = true,
+        Some("history-search") => start_history_search(app),
+        Some("send") if app.story_reply.is_some() => send_story_reply(signal, app),
+        Some("send") => {
+            let typed = app.input.text().trim().to_string();
+            if send_text(signal, app, &typed) {
+                app.status = "sent".to_string();
+                remember_input(app, &typed);
+                app.input.clear();
+                stash_draft(app);
+                app.mode = Mode::Normal;
+            }
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+/// Sends composer text (with markup) to the selected chat. Returns true if sent;
+/// otherwise the reason is in the status line.
+fn send_text(signal: &SignalCli, app: &mut App, typed: &str) -> bool {
+    let (body, styles) = text_style::parse_markup(typed.trim());
+    if body.is_empty() {
+        app.status = "empty message; nothing sent".to_string();
+        return false;
+    }
+    let Some(t) = app.selected_target().cloned() else {
+        app.status = "no target selected".to_string();
+        return false;
+    };
+
+    let send_res = match t.kind {
+        TargetKind::Contact => signal.send_message_to_number(&app.acct().account, &t.addr, &body, &styles),
+        TargetKind::Group => signal.send_message_to_group(&app.acct().account, &t.addr, &body, &styles),
+    };
+    match send_res {
+        Ok(()) => {
+            record_outgoing(app, &t, body, styles, None);
+            true
+        }
+        Err(e) => {
+            report_send_error(app, &t, e);
+            false
+        }
+    }
+}
+
+/// Keeps the composer text as the selected chat's draft (an empty composer
+/// drops the draft) and persists the account's drafts where allowed.
+fn stash_draft(app: &mut App) {
+    // Story replies are one-off; they do not replace the chat's draft.
+    if app.story_reply.is_some() {
+        return;
+    }
+    let Some(key) = app.selected_target().map(|t| t.conversation_key.clone()) else { return; };
+    let text = app.input.text().to_string();
+    let acct = &mut app.accounts[app.active];
+    let changed = if text.trim().is_empty() {
+        acct.drafts.remove(&key).is_some()
+    } else {
+        acct.drafts.insert(key, text.clone()) != Some(text)
+    };
+    if changed && let Err(e) = persist_drafts(&app.cfg, acct) {
+        app.status = format!("save draft: {e:#}");
+    }
+}
+
+/// Saves the account's drafts that may go to disk (see `keeps_on_disk`); the
+/// others are only kept for the session.
+fn persist_drafts(cfg: &config::Config, acct: &AccountState) -> Result<()> {
+    let kept: HashMap<String, String> = acct
+        .drafts
+        .iter()
+        .filter(|(k, _)| keeps_on_disk(cfg, acct, k))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    state::save_drafts(&cfg.state_dir, &acct.account, &kept)
+}
+
+/// Loads the selected chat's draft (if any) into the composer.
+fn restore_draft(app: &mut App) {
+    let draft = app
+        .selected_target()
+        .and_then(|t| app.acct().drafts.get(&t.conversation_key))
+        .cloned()
+        .unwrap_or_default();
+    app.input.set_text(draft);
+}
+
+fn send_story_reply(signal: &SignalCli, app: &mut App) {
+    let Some(story) = app.story_reply.clone() else { return; };
+    let body = app.input.text().trim().to_string();
+    if body.is_empty() {
+        app.status = "empty message; nothing sent".to_string();
+        return;
+    }
+    let Some(t) = app.selected_target().cloned() else {
+        app.status = "no target selected".to_string();
+        return;
+    };
+    let account = app.acct().account.clone();
+    match signal.send_story_reply(&account, &story.author, story.timestamp_ms, &body) {
+        Ok(()) => {
+            let quoted = format!("[re story: {}] {body}", story_snippet(&story));
+            record_outgoing(app, &t, quoted, Vec::new(), None);
+            app.status = "story reply sent".to_string();
+            remember_input(app, &body);
+            app.input.clear();
+            app.story_reply = None;
+            app.mode = Mode::Normal;
+        }
+        Err(e) => report_send_error(app, &t, e),
+    }
+}
+
+/// Adds a message we just sent to the chat and to scrollback.
+fn record_outgoing(app: &mut App, t: &Target, body: String, styles: Vec<StyleRange>, sticker: Option<Sticker>) {
+    let me = app.acct().account.clone();
+    let timer = app.acct().timers.get(&t.conversation_key).copied().unwrap_or(0);
+    let expires_at_ms = expiry_for(timer);
+    let now = now_ms();
+    if app.cfg.save_scrollback {
+        let rec = ScrollbackRecord {
+            ts_ms: Some(now),
+            dir: "out".to_string(),
+            who: Some(me.clone()),
+            body: body.clone(),
+            expires_in_secs: (timer > 0).then_some(timer),
+            expires_at_ms,
+            view_once_paths: Vec::new(),
+            text_styles: styles.clone(),
+            sticker: sticker.clone(),
+        };
+        let _ = scrollback::append(&app.cfg.scrollback_dir, &me, &t.conversation_key, &rec);
+    }
+    touch(app.acct_mut(), &t.conversation_key, Some(now));
+    let acct = app.acct_mut();
+    if let Some(i) = acct.archived.iter().position(|k| *k == t.conversation_key) {
+        acct.archived.remove(i);
+        if let Err(e) = state::save_archived(&app.cfg.state_dir, &app.acct().account, &app.acct().archived) {
+            app.status = format!("{e:#}");
+        }
+    }
+    app.acct_mut().messages.entry(t.conversation_key.clone()).or_default().push(ChatMessage {
+        ts_ms: Some(now),
+        dir: MsgDir::Out,
+        who: Some(me),
+        body,
+        expires_in_secs: timer,
+        expires_at_ms,
+        view_once: Vec::new(),
+        styles,
+        sticker,
+    });
+    // With `sort = "recent"` the chat moves up; the selection stays on it.
+    let view = app.chat_view();
+    sort_targets(app.acct_mut(), view);
+}
+
+fn report_send_error(app: &mut App, t: &Target, e: anyhow::Error) {
+    let err = format!("{e:#}");
+    if err.to_lowercase().contains("untrusted") {
+        app.acct_mut().identity_alerts.insert(t.conversation_key.clone(), err.clone());
+    }
+    app.status = format!("send error: {err}");
+}
+
+fn handle_key_add_recipient(app: &mut App, k: KeyEvent) -> Result<bool> {
+    let fed = app.cfg.keys.feed(KeyMode::Prompt, &mut app.pending_keys, k);
+    for k in fed.unbound {
+        edit_or_recall(app, k, false);
+    }
+    match fed.action.as_deref() {
+        Some("cancel") => {
+            app.mode = Mode::Normal;
+            app.input.clear();
+            app.status = "cancelled".to_string();
+        }
+        Some("accept") => {
+            let num = app.input.text().trim().to_string();
+            match add_recipient(app, &num) {
+                Ok(()) => {
+                    remember_input(app, &num);
+                    app.mode = Mode::Normal;
+                    app.input.clear();
+                }
+                Err(e) => app.status = format!("{e:#}"),
+            }
+        }
+        Some("history-search") => start_history_search(app),
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn add_recipient(app: &mut App, num: &str) -> Result<()> {
+    if !num.starts_with('+') || num.len() < 8 {
+        bail!("recipient must look like +15551234567");
+    }
+    let key = format!("contact:{num}");
+    if !app.acct().targets.iter().any(|t| t.conversation_key == key) {
+        let display = app.cfg.aliases.get(num).cloned().unwrap_or_else(|| num.to_string());
+        app.acct_mut().targets.push(Target {
+            conversation_key: key.clone(),
+            kind: TargetKind::Contact,
+            addr: num.to_string(),
+            display,
+            name: None,
+        });
+    }
+    select_chat(app, &key);
+    app.status = format!("recipient added ({} to message)", app.cfg.keys.press(KeyMode::Normal, "insert"));
+    Ok(())
+}
+
+fn open_view_once(app: &mut App) {
+    let Some(key) = app.selected_target().map(|t| t.conversation_key.clone()) else {
+        app.status = "no target selected".to_string();
+        return;
+    };
+    let Some(paths) = app
+        .acct()
+        .messages
+        .get(&key)
+        .and_then(|msgs| msgs.iter().rev().find(|m| !m.view_once.is_empty()))
+        .map(|m| m.view_once.clone())
+    else {
+        app.status = "no unopened view-once media in this chat".to_string();
+        return;
+    };
+
+    for path in &paths {
+        if let Err(e) = std::process::Command::new("xdg-open")
+            .arg(path)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+        {
+            app.status = format!("could not open view-once media: {e}");
+            return;
+        }
+    }
+
+    let account = app.acct().account.clone();
+    if let Some(m) = app
+        .acct_mut()
+        .messages
+        .get_mut(&key)
+        .and_then(|msgs| msgs.iter_mut().rev().find(|m| m.view_once == paths))
+    {
+        m.view_once.clear();
+        m.body = "[viewed]".to_string();
+    }
+    let path_strs: Vec<String> = paths.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+    let _ = scrollback::mark_viewed(&app.cfg.scrollback_dir, &account, &key, &path_strs, "[viewed]");
+
+    // Give the viewer a moment to load the files before they disappear.
+    let ps = paths.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_secs(30));
+        for p in ps {
+            let _ = fs::remove_file(p);
+        }
+    });
+    app.wipe_on_exit.extend(paths);
+    app.status = "opened view-once media; it will be deleted shortly".to_string();
+}
+
+fn handle_key_stories(app: &mut App, k: KeyEvent) -> Result<bool> {
+    match popup_key(&app.cfg.keys, &k) {
+        Some(PopupKey::Cancel) => app.mode = Mode::Normal,
+        Some(PopupKey::Down) => {
+            app.story_cursor = (app.story_cursor + 1).min(app.acct().stories.len().saturating_sub(1));
+        }
+        Some(PopupKey::Up) => {
+            app.story_cursor = app.story_cursor.saturating_sub(1);
+        }
+        Some(PopupKey::Accept | PopupKey::Reply) => {
+            if let Err(e) = reply_to_story(app) {
+                app.status = format!("{e:#}");
+            }
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+/// Opens the story author's chat to reply to the story under the cursor, or
+/// to the newest story outside the story list.
+fn reply_to_story(app: &mut App) -> Result<()> {
+    let stories = &app.acct().stories;
+    let story = if app.mode == Mode::Stories {
+        stories.get(app.story_cursor)
+    } else {
+        stories.last()
+    };
+    let Some(story) = story.cloned() else {
+        app.mode = Mode::Normal;
+        bail!("no stories");
+    };
+    if !story.allows_replies {
+        bail!("this story does not allow replies");
+    }
+    // Replies go to the author's own chat.
+    let key = format!("contact:{}", story.author);
+    if !app.acct().targets.iter().any(|t| t.conversation_key == key) {
+        let display = contact_display(app.acct(), &app.cfg, &story.author);
+        app.acct_mut().targets.push(Target {
+            conversation_key: key.clone(),
+            kind: TargetKind::Contact,
+            addr: story.author.clone(),
+            display,
+            name: None,
+        });
+    }
+    select_chat(app, &key);
+    app.status = format!("replying to story: {}", story_snippet(&story));
+    app.story_reply = Some(story);
+    app.input.clear();
+    app.mode = Mode::Insert;
+    Ok(())
+}
+
+fn story_snippet(st: &Story) -> String {
+    if let Some(text) = &st.text {
+        let mut t: String = text.chars().take(40).collect();
+        if text.chars().count() > 40 {
+            t.push_str("...");
+        }
+        t
+    } else if let Some(a) = &st.attachment {
+        format!("[{}]", media_kind(a))
+    } else {
+        "[story]".to_string()
+    }
+}
+
+/// Display names of the incoming senders in `msgs`, keyed by number/UUID.
+/// Senders who resolve to the same name get the end of their number (or the
+/// start of their UUID) appended so their lines can be told apart.
+fn sender_names(acct: &AccountState, cfg: &config::Config, msgs: &[ChatMessage]) -> HashMap<String, String> {
+    let mut names: HashMap<String, String> = HashMap::new();
+    for m in msgs {
+        if let (MsgDir::In, Some(id)) = (&m.dir, &m.who)
+            && !names.contains_key(id)
+        {
+            names.insert(id.clone(), contact_display(acct, cfg, id));
+        }
+    }
+    let mut by_name: HashMap<&str, usize> = HashMap::new();
+    for name in names.values() {
+        *by_name.entry(name.as_str()).or_default() += 1;
+    }
+    let shared: HashSet<String> = by_name
+        .into_iter()
+        .filter(|(_, n)| *n > 1)
+        .map(|(name, _)| name.to_string())
+        .collect();
+    for (id, name) in names.iter_mut() {
+        if shared.contains(name.as_str()) {
+            let tag = if id.starts_with('+') {
+                format!("…{}", &id[id.len().saturating_sub(4)..])
+            } else {
+                id.chars().take(8).collect()
+            };
+            name.push_str(&format!(" ({tag})"));
+        }
+    }
+    names
+}
+
+/// Best display name for a phone number in this account.
+fn contact_display(acct: &AccountState, cfg: &config::Config, number: &str) -> String {
+    let key = format!("contact:{number}");
+    acct.targets
+        .iter()
+        .find(|t| t.conversation_key == key)
+        .map(|t| t.display.clone())
+        .or_else(|| cfg.aliases.get(number).cloned())
+        .unwrap_or_else(|| number.to_string())
+}
+
+/// Loads `acct`'s sticker packs when the first sticker arrives, so its label
+/// (which also goes to scrollback) gets the pack title.
+fn load_sticker_packs_for(signal: &SignalCli, acct: &mut AccountState, rx: &Received) {
+    if acct.sticker_packs.is_none()
+        && rx.messages.iter().any(|m| m.sticker.is_some())
+        && let Ok(packs) = signal.list_sticker_packs(&acct.account)
+    {
+        acct.sticker_packs = Some(packs);
+    }
+}
+
+fn open_sticker_picker(signal: &SignalCli, app: &mut App) {
+    if app.selected_target().is_none() {
+        app.status = "no target selected".to_string();
+        return;
+    }
+    // Refresh so packs installed on the phone since startup show up.
+    let account = app.acct().account.clone();
+    if let Ok(packs) = signal.list_sticker_packs(&account) {
+        app.acct_mut().sticker_packs = Some(packs);
+    }
+    let choices: Vec<Sticker> = app
+        .acct()
+        .sticker_packs
+        .iter()
+        .flatten()
+        .filter(|p| p.installed)
+        .flat_map(|p| {
+            p.stickers.iter().map(|s| Sticker {
+                pack_id: p.pack_id.clone(),
+                sticker_id: s.id,
+                emoji: s.emoji.clone(),
+            })
+        })
+        .collect();
+    if choices.is_empty() {
+        app.status = "no installed sticker packs".to_string();
+        return;
+    }
+    app.sticker_choices = choices;
+    app.sticker_cursor = 0;
+    app.mode = Mode::StickerPicker;
+}
+
+fn handle_key_sticker_picker(signal: &SignalCli, app: &mut App, k: KeyEvent) -> Result<bool> {
+    match popup_key(&app.cfg.keys, &k) {
+        Some(PopupKey::Cancel) => {
+            app.mode = Mode::Normal;
+            app.sticker_choices.clear();
+        }
+        Some(PopupKey::Down) => {
+            app.sticker_cursor = (app.sticker_cursor + 1).min(app.sticker_choices.len().saturating_sub(1));
+        }
+        Some(PopupKey::Up) => {
+            app.sticker_cursor = app.sticker_cursor.saturating_sub(1);
+        }
+        Some(PopupKey::Accept) => {
+            let Some(st) = app.sticker_choices.get(app.sticker_cursor).cloned() else {
+                return Ok(false);
+            };
+            let Some(t) = app.selected_target().cloned() else {
+                app.status = "no target selected".to_string();
+                return Ok(false);
+            };
+            let arg = format!("{}:{}", st.pack_id, st.sticker_id);
+            let account = app.acct().account.clone();
+            let res = match t.kind {
+                TargetKind::Contact => signal.send_sticker_to_number(&account, &t.addr, &arg),
+                TargetKind::Group => signal.send_sticker_to_group(&account, &t.addr, &arg),
+            };
+            match res {
+                Ok(()) => {
+                    let label = sticker_label(app.acct().sticker_packs.as_deref().unwrap_or_default(), &st);
+                    record_outgoing(app, &t, label, Vec::new(), Some(st));
+                    app.status = "sticker sent".to_string();
+                    app.mode = Mode::Normal;
+                    app.sticker_choices.clear();
+                }
+                Err(e) => report_send_error(app, &t, e),
+            }
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+/// `[sticker: 😀 packname]`, filling in emoji and pack title from known packs.
+fn sticker_label(packs: &[StickerPack], st: &Sticker) -> String {
+    let pack = packs.iter().find(|p| p.pack_id == st.pack_id);
+    let emoji = st.emoji.clone().or_else(|| {
+        pack?.stickers.iter().find(|s| s.id == st.sticker_id)?.emoji.clone()
+    });
+    let title = pack.and_then(|p| p.title.clone());
+    let parts: Vec<String> = [emoji, title].into_iter().flatten().collect();
+    if parts.is_empty() {
+        "[sticker]".to_string()
+    } else {
+        format!("[sticker: {}]", parts.join(" "))
+    }
+}
+
+fn media_kind(a: &Attachment) -> &'static str {
+    match a.content_type.as_deref() {
+        Some(t) if t.starts_with("image/") => "photo",
+        Some(t) if t.starts_with("video/") => "video",
+        _ => "media",
+    }
+}
+
+fn open_identity_view(signal: &SignalCli, app: &mut App) {
+    let Some(t) = app.selected_target().cloned() else {
+        app.status = "no target selected".to_string();
+        return;
+    };
+    if t.kind != TargetKind::Contact {
+        app.status = "safety numbers are per contact; select a contact".to_string();
+        return;
+    }
+    match signal.list_identities(&app.acct().account, &t.addr) {
+        Ok(ids) if ids.is_empty() => {
+            app.status = format!("no identity known for {} yet", t.display);
+        }
+        Ok(ids) => {
+            app.identities = ids;
+            app.mode = Mode::Identity;
+        }
+        Err(e) => app.status = format!("identity error: {e:#}"),
+    }
+}
+
+fn handle_key_identity(signal: &SignalCli, app: &mut App, k: KeyEvent) -> Result<bool> {
+    match popup_key(&app.cfg.keys, &k) {
+        Some(PopupKey::Cancel) => {
+            app.mode = Mode::Normal;
+            app.identities.clear();
+        }
+        Some(PopupKey::Trust) => trust_identities(signal, app)?,
+        _ => {}
+    }
+    Ok(false)
+}
+
+/// Marks the selected contact's safety numbers verified: those shown in the
+/// identity view, or all that signal-cli knows outside it.
+fn trust_identities(signal: &SignalCli, app: &mut App) -> Result<()> {
+    let t = app.selected_target().cloned().context("no target selected")?;
+    if t.kind != TargetKind::Contact {
+        bail!("safety numbers are per contact; select a contact");
+    }
+    let account = app.acct().account.clone();
+    let ids = if app.mode == Mode::Identity {
+        app.identities.clone()
+    } else {
+        signal.list_identities(&account, &t.addr).context("identity error")?
+    };
+    if ids.is_empty() {
+        bail!("no identity known for {} yet", t.display);
+    }
+    // A contact with several devices (or an old and a new key) lists one entry
+    // per key; verify every one so none stays untrusted.
+    let failed: Vec<String> = ids
+        .iter()
+        .filter_map(|id| signal.trust_verified(&account, &t.addr, &id.safety_number).err())
+        .map(|e| format!("{e:#}"))
+        .collect();
+    if failed.is_empty() {
+        app.acct_mut().identity_alerts.remove(&t.conversation_key);
+        app.status = format!("marked {} as verified", t.display);
+    } else {
+        app.status = format!("trust error: {}", failed.join("; "));
+    }
+    // Re-read so the popup shows the new trust levels.
+    if app.mode == Mode::Identity
+        && let Ok(ids) = signal.list_identities(&account, &t.addr)
+    {
+        app.identities = ids;
+    }
+    Ok(())
+}
+
+fn handle_key_set_timer(signal: &SignalCli, app: &mut App, k: KeyEvent) -> Result<bool> {
+    let fed = app.cfg.keys.feed(KeyMode::Prompt, &mut app.pending_keys, k);
+    for k in fed.unbound {
+        edit_or_recall(app, k, false);
+    }
+    match fed.action.as_deref() {
+        Some("cancel") => {
+            app.mode = Mode::Normal;
+            app.input.clear();
+            app.status = "cancelled".to_string();
+        }
+        Some("accept") => {
+            let typed = app.input.text().trim().to_string();
+            match set_timer(signal, app, &typed) {
+                Ok(()) => {
+                    remember_input(app, &typed);
+                    app.mode = Mode::Normal;
+                    app.input.clear();
+                }
+                Err(e) => app.status = format!("{e:#}"),
+            }
+        }
+        Some("history-search") => start_history_search(app),
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn set_timer(signal: &SignalCli, app: &mut App, spec: &str) -> Result<()> {
+    let Some(secs) = parse_timer(spec) else {
+        bail!("timer must look like 30s, 5m, 8h, 1d, 4w or off");
+    };
+    let Some(t) = app.selected_target().cloned() else {
+        bail!("no target selected");
+    };
+    let account = app.acct().account.clone();
+    match t.kind {
+        TargetKind::Contact => signal.set_expiration_timer_for_number(&account, &t.addr, secs),
+        TargetKind::Group => signal.set_expiration_timer_for_group(&account, &t.addr, secs),
+    }
+    .context("timer error")?;
+    let acct = &mut app.accounts[app.active];
+    record_timer(&app.cfg, acct, &t.conversation_key, secs)?;
+    if secs > 0 {
+        forget_history(&mut app.history, &mut app.status, &account, &t.conversation_key);
+    }
+    app.status = if secs == 0 {
+        "disappearing messages off".to_string()
+    } else {
+        format!("disappearing messages: {}", format_duration(secs as i64))
+    };
+    Ok(())
+}
+
+fn handle_key_account_picker(app: &mut App, k: KeyEvent) -> Result<bool> {
+    match popup_key(&app.cfg.keys, &k) {
+        Some(PopupKey::Cancel) => {
+            app.mode = Mode::Normal;
+            app.status = format!("using account {}", app.acct().account);
+        }
+        Some(PopupKey::Down) => {
+            app.account_cursor = (app.account_cursor + 1).min(app.accounts.len() - 1);
+        }
+        Some(PopupKey::Up) => {
+            app.account_cursor = app.account_cursor.saturating_sub(1);
+        }
+        Some(PopupKey::Accept) => switch_account(app, app.account_cursor),
+        _ => {}
+    }
+    Ok(false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PopupKey {
+    Down,
+    Up,
+    Accept,
+    Cancel,
+    Trust,
+    Reply,
+}
+
+/// Keys of the list popups, from `[keys.popup]`.
+fn popup_key(keys: &Keymap, k: &KeyEvent) -> Option<PopupKey> {
+    [
+        ("down", PopupKey::Down),
+        ("up", PopupKey::Up),
+        ("accept", PopupKey::Accept),
+        ("cancel", PopupKey::Cancel),
+        ("trust", PopupKey::Trust),
+        ("reply", PopupKey::Reply),
+    ]
+    .into_iter()
+    .find(|(action, _)| keys.matches(KeyMode::Popup, action, k))
+    .map(|(_, key)| key)
+}
+
+/// Status line help of a list popup, e.g. "j/Down down, k/Up up, Enter send, Esc/q cancel".
+fn popup_help(keys: &Keymap, accept: &str, cancel: &str) -> String {
+    keys.help(KeyMode::Popup, &[("down", "down"), ("up", "up"), ("accept", accept), ("cancel", cancel)])
+}
+
+/// Draws a frame; returns where sticker images should go on top of it.
+fn ui(f: &mut Frame, app: &App) -> Vec<ImagePlacement> {
+    let root = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(status_height(app))])
+        .split(f.area());
+
+    let main = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        .split(root[0]);
+
+    draw_targets(f, app, main[0]);
+    let mut images = draw_chat(f, app, main[1]);
+    draw_status(f, app, root[1]);
+
+    match app.mode {
+        Mode::Identity => draw_identity(f, app, f.area()),
+        Mode::AccountPicker => draw_account_picker(f, app, f.area()),
+        Mode::StickerPicker => draw_sticker_picker(f, app, f.area()),
+        Mode::Stories => draw_stories(f, app, f.area()),
+        Mode::Finder => draw_finder(f, app, f.area()),
+        _ => {}
+    }
+    // Kitty draws images above the text, so they would cover popups.
+    if matches!(
+        app.mode,
+        Mode::Identity | Mode::AccountPicker | Mode::StickerPicker | Mode::Stories | Mode::Finder
+    ) {
+        images.clear();
+    }
+
+    // NO_COLOR: one pass instead of checking at every colored span.
+    if app.cfg.theme.no_color {
+        for cell in &mut f.buffer_mut().content {
+            cell.set_fg(Color::Reset).set_bg(Color::Reset);
+        }
+    }
+    images
+}
+
+fn bordered(app: &App) -> Block<'static> {
+    Block::default().borders(Borders::ALL).border_style(app.cfg.theme.border)
+}
+
+fn draw_targets(f: &mut Frame, app: &App, area: Rect) {
+    let acct = app.acct();
+    let stories = acct.stories.len();
+    let title = match (app.show_archived, stories) {
+        (true, _) => format!("Archived ({})", acct.shown),
+        (false, 0) => format!("Chats ({})", acct.shown),
+        (false, _) => format!("Chats ({})  stories: {stories}", acct.shown),
+    };
+    let block = bordered(app).title(title);
+    let mut inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Last row: the way into (or back out of) the archive.
+    let archive_key = app.cfg.keys.keys_for(KeyMode::Normal, "archived").into_iter().next();
+    let hint = archive_key.map(|k| format!("  {k}")).unwrap_or_default();
+    let footer = if app.show_archived {
+        Some(format!("Back to chats{hint}"))
+    } else if !acct.archived.is_empty() {
+        Some(format!("Archived ({}){hint}", acct.archived.len()))
+    } else {
+        None
+    };
+    if let Some(footer) = footer
+        && inner.height > 1
+    {
+        inner.height -= 1;
+        let row = Rect { y: inner.y + inner.height, height: 1, ..inner };
+        f.render_widget(Paragraph::new(Span::styled(footer, app.cfg.theme.dim)), row);
+    }
+
+    // Long lists (e.g. --all-recipients): keep the selection on screen.
+    let now = now_ms();
+    let visible = inner.height.max(1) as usize;
+    let first = acct.selected.saturating_sub(visible - 1);
+    let items: Vec<ListItem> = acct.targets[..acct.shown]
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .map(|(i, t)| {
+            let unread = *app.acct().unread.get(&t.conversation_key).unwrap_or(&0);
+            let muted = chat_rule(acct, &app.cfg, &t.conversation_key).muted(now);
+            let mut style = match unread {
+                0 => Style::default(),
+                _ if muted => app.cfg.theme.dim,
+                _ => app.cfg.theme.unread,
+            };
+            if i == app.acct().selected {
+                style = app.cfg.theme.selection;
             }
             let prefix = match t.kind {
                 TargetKind::Contact => "@",
                 TargetKind::Group => "#",
             };
             let badge = if unread > 0 { format!(" ({unread})") } else { String::new() };
-            ListItem::new(Line::from(vec![
+            let mut spans = vec![
                 Span::styled(prefix, style),
                 Span::raw(" "),
                 Span::styled(format!("{}{}", t.display, badge), style),
-            ]))
+            ];
+            if app.acct().pinned.contains(&t.conversation_key) {
+                let pin_style = if i == app.acct().selected { style } else { app.cfg.theme.dim };
+                spans.push(Span::styled(" [pin]", pin_style));
+            }
+            if app.acct().drafts.contains_key(&t.conversation_key) {
+                let draft_style = if i == app.acct().selected { style } else { app.cfg.theme.dim };
+                spans.push(Span::styled(" [draft]", draft_style.add_modifier(Modifier::ITALIC)));
+            }
+            ListItem::new(Line::from(spans))
         })
         .collect();
-
-    let title = format!("Chats ({})", app.targets.len());
-    let list = List::new(items).block(Block::default().borders(Borders::ALL).title(title));
-    f.render_widget(list, area);
+    f.render_widget(List::new(items), inner);
 }
 
-fn draw_chat(f: &mut Frame, app: &App, area: Rect) {
+/// Draws the open chat; returns where its sticker images go.
+fn draw_chat(f: &mut Frame, app: &App, area: Rect) -> Vec<ImagePlacement> {
     let title = if let Some(t) = app.selected_target() {
-        format!("{}  [{}]", t.display, t.addr)
+        let mut title = format!("{}  [{}]", t.display, t.addr);
+        if let Some(&secs) = app.acct().timers.get(&t.conversation_key)
+            && secs > 0
+        {
+            title.push_str(&format!("  disappearing: {}", format_duration(secs as i64)));
+        }
+        let rule = chat_rule(app.acct(), &app.cfg, &t.conversation_key);
+        let now = now_ms();
+        match rule.mute {
+            Some(Mute::Forever) => title.push_str("  muted"),
+            Some(Mute::Until(at)) if at > now => {
+                title.push_str(&format!("  muted: {} left", format_duration((at - now) / 1000)));
+            }
+            _ if rule.mentions_only == Some(true) => title.push_str("  mentions only"),
+            _ => {}
+        }
+        title
     } else {
         "No chat selected".to_string()
     };
@@ -587,13 +2647,19 @@ fn draw_chat(f: &mut Frame, app: &App, area: Rect) {
     let key = app.selected_target().map(|t| t.conversation_key.clone());
     let msgs = key
         .as_deref()
-        .and_then(|k| app.messages.get(k))
+        .and_then(|k| app.acct().messages.get(k))
         .map(|v| v.as_slice())
         .unwrap_or(&[]);
 
     // Render last N lines. Keep it simple: no scroll yet.
+    let msgs = &msgs[msgs.len().saturating_sub(200)..];
+    let names = sender_names(app.acct(), &app.cfg, msgs);
+    let now = now_ms();
+    let reveal = key.as_deref().is_some_and(|k| app.revealed_spoilers.contains(k));
     let mut lines = Vec::new();
-    for m in msgs.iter().rev().take(200).rev() {
+    // (index of the first blank line under the label, sticker, image)
+    let mut stickers = Vec::new();
+    for m in msgs {
         let ts = m
             .ts_ms
             .map(|t| format!("{}", t / 1000))
@@ -601,58 +2667,600 @@ fn draw_chat(f: &mut Frame, app: &App, area: Rect) {
         let dir = match m.dir {
             MsgDir::In => "<",
             MsgDir::Out => ">",
+            MsgDir::System => {
+                let style = app.cfg.theme.system;
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{ts} * "), app.cfg.theme.dim),
+                    Span::styled(m.body.clone(), style),
+                ]));
+                continue;
+            }
+        };
+        let who = match (&m.dir, &m.who) {
+            (MsgDir::Out, _) => app.cfg.self_name.clone(),
+            (_, Some(id)) => names.get(id.as_str()).cloned().unwrap_or_else(|| id.clone()),
+            (_, None) => "?".to_string(),
+        };
+        let hit = app.chat_search.as_ref().is_some_and(|q| m.body.to_lowercase().contains(q));
+        let head_style = match (hit, &m.dir) {
+            (true, _) => app.cfg.theme.highlight,
+            (false, MsgDir::Out) => app.cfg.theme.outgoing,
+            (false, _) => app.cfg.theme.incoming,
+        };
+        let who_style = match (hit, &m.dir, &m.who) {
+            (false, MsgDir::In, Some(id)) => app.cfg.theme.sender(id),
+            _ => head_style,
+        };
+        let mut spans = vec![
+            Span::styled(format!("{ts} {dir} "), head_style),
+            Span::styled(who, who_style),
+            Span::styled(": ", head_style),
+        ];
+        if let Some(at) = m.expires_at_ms {
+            let left = ((at - now) / 1000).max(0);
+            spans.push(Span::styled(format!("[{}] ", format_duration(left)), app.cfg.theme.system));
+        }
+        spans.extend(text_style::styled_spans(&m.body, &m.styles, reveal, &app.cfg.theme));
+        if !m.view_once.is_empty() {
+            let hint = format!(" {} to open (once)", app.cfg.keys.press(KeyMode::Normal, "view-once"));
+            spans.push(Span::styled(hint, app.cfg.theme.highlight));
+        }
+        lines.extend(split_lines(spans));
+        if let Some(st) = &m.sticker {
+            let id = (st.pack_id.clone(), st.sticker_id);
+            if let Some(StickerImage::Ready(img)) = app.sticker_images.get(&id) {
+                // Room under the label; the run loop draws the image there.
+                stickers.push((lines.len(), id, img));
+                lines.extend((0..img.rows).map(|_| Line::raw("")));
+            }
+        }
+    }
+
+    let mut area = area;
+    if let Some(alert) = key.as_deref().and_then(|k| app.acct().identity_alerts.get(k)) {
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)])
+            .split(area);
+        let warn = app.cfg.theme.warning.add_modifier(Modifier::REVERSED);
+        let banner = Paragraph::new(Line::from(vec![
+            Span::styled(" Safety number changed ", warn),
+            Span::raw(format!(
+                " messages may not be delivered; {} to verify  ",
+                app.cfg.keys.press(KeyMode::Normal, "verify")
+            )),
+            Span::styled(alert.clone(), app.cfg.theme.dim),
+        ]))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(app.cfg.theme.warning),
+        );
+        f.render_widget(banner, parts[0]);
+        area = parts[1];
+    }
+
+    let block = bordered(app).title(title);
+    let placements = image_placements(&lines, &stickers, block.inner(area));
+    let p = Paragraph::new(lines).block(block).wrap(Wrap { trim: false });
+    f.render_widget(p, area);
+    placements
+}
+
+/// Where the sticker images land once `lines` are wrapped into `area`;
+/// images that would be cut off are left out (their label still shows).
+fn image_placements(
+    lines: &[Line],
+    stickers: &[(usize, (String, u32), &graphics::Image)],
+    area: Rect,
+) -> Vec<ImagePlacement> {
+    let mut tops = Vec::with_capacity(lines.len());
+    let mut y = 0;
+    for line in lines {
+        tops.push(y);
+        y += Paragraph::new(line.clone()).wrap(Wrap { trim: false }).line_count(area.width);
+    }
+    stickers
+        .iter()
+        .filter_map(|(at, id, img)| {
+            let top = *tops.get(*at)?;
+            let fits = top + usize::from(img.rows) <= usize::from(area.height) && img.cols + 2 <= area.width;
+            fits.then(|| ImagePlacement { x: area.x + 2, y: area.y + top as u16, sticker: id.clone() })
+        })
+        .collect()
+}
+
+/// Fetches the images of the open chat's stickers that were not asked for yet.
+fn request_sticker_images(app: &mut App, tx: &mpsc::Sender<(String, Sticker)>) {
+    if app.cfg.graphics.is_none() {
+        return;
+    }
+    let Some(key) = app.selected_target().map(|t| t.conversation_key.clone()) else { return; };
+    let account = app.acct().account.clone();
+    let wanted: Vec<Sticker> = app
+        .acct()
+        .messages
+        .get(&key)
+        .map(|v| v.iter().rev().take(200).filter_map(|m| m.sticker.clone()).collect())
+        .unwrap_or_default();
+    for st in wanted {
+        if let Entry::Vacant(e) = app.sticker_images.entry((st.pack_id.clone(), st.sticker_id)) {
+            e.insert(StickerImage::Loading);
+            let _ = tx.send((account.clone(), st));
+        }
+    }
+}
+
+/// Draws sticker images into the rows `draw_chat` left blank for them, only
+/// when something moved. Images are not part of ratatui's buffer: kitty
+/// placements need an explicit delete, sixel leftovers a full repaint.
+fn draw_images(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+    images: (Rect, Vec<ImagePlacement>),
+) -> Result<()> {
+    let Some(protocol) = app.cfg.graphics else { return Ok(()); };
+    if images == app.drawn_images {
+        return Ok(());
+    }
+    match protocol {
+        graphics::Protocol::Kitty => {
+            terminal.backend_mut().write_all(graphics::KITTY_CLEAR.as_bytes()).context("clear images")?;
+        }
+        graphics::Protocol::Sixel if !app.drawn_images.1.is_empty() => {
+            terminal.clear().context("clear terminal")?;
+            terminal.draw(|f| {
+                ui(f, app);
+            })?;
+        }
+        graphics::Protocol::Sixel => {}
+    }
+    let out = terminal.backend_mut();
+    queue!(out, SavePosition).context("draw images")?;
+    for p in &images.1 {
+        let Some(StickerImage::Ready(img)) = app.sticker_images.get(&p.sticker) else { continue; };
+        if let Some(data) = img.transmit()
+            && app.sticker_uploaded.insert(p.sticker.clone())
+        {
+            out.write_all(data.as_bytes()).context("upload image")?;
+        }
+        queue!(out, MoveTo(p.x, p.y)).context("draw images")?;
+        out.write_all(img.place().as_bytes()).context("draw image")?;
+    }
+    queue!(out, RestorePosition).context("draw images")?;
+    out.flush().context("draw images")?;
+    app.drawn_images = images;
+    Ok(())
+}
+
+/// Breaks spans containing '\n' into separate lines (multi-line messages).
+fn split_lines(spans: Vec<Span<'static>>) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut cur = Vec::new();
+    for span in spans {
+        let mut parts = span.content.split('\n').peekable();
+        while let Some(part) = parts.next() {
+            if !part.is_empty() {
+                cur.push(Span::styled(part.to_string(), span.style));
+            }
+            if parts.peek().is_some() {
+                lines.push(Line::from(std::mem::take(&mut cur)));
+            }
+        }
+    }
+    lines.push(Line::from(cur));
+    lines
+}
+
+fn draw_identity(f: &mut Frame, app: &App, area: Rect) {
+    let title = app
+        .selected_target()
+        .map(|t| format!("Safety number: {}", t.display))
+        .unwrap_or_else(|| "Safety number".to_string());
+
+    let mut lines = Vec::new();
+    for id in &app.identities {
+        let trust_style = match id.trust_level.as_str() {
+            "TRUSTED_VERIFIED" => app.cfg.theme.status.add_modifier(Modifier::BOLD),
+            "UNTRUSTED" => app.cfg.theme.warning,
+            _ => app.cfg.theme.system,
         };
-        let who = m.who.clone().unwrap_or_else(|| "?".to_string());
+        let addr = id.number.as_deref().or(id.uuid.as_deref()).unwrap_or("?");
         lines.push(Line::from(vec![
-            Span::styled(format!("{ts} {dir} {who}: "), Style::default().fg(Color::Gray)),
-            Span::raw(m.body.clone()),
+            Span::raw(format!("{addr}  ")),
+            Span::styled(id.trust_level.clone(), trust_style),
         ]));
+        lines.push(Line::raw(""));
+        lines.push(Line::styled("Safety number:", app.cfg.theme.dim));
+        for row in format_safety_number(&id.safety_number) {
+            lines.push(Line::raw(format!("  {row}")));
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::styled("Fingerprint:", app.cfg.theme.dim));
+        lines.push(Line::raw(format!("  {}", id.fingerprint)));
+        lines.push(Line::raw(""));
     }
+    let keys = app.cfg.keys.help(KeyMode::Popup, &[("trust", "mark verified"), ("cancel", "close")]);
+    lines.push(Line::styled(format!("Compare with the number on their device, then: {keys}"), app.cfg.theme.dim));
 
+    let popup = centered_rect(70, 60, area);
     let p = Paragraph::new(lines)
-        .block(Block::default().borders(Borders::ALL).title(title))
+        .block(bordered(app).title(title))
         .wrap(Wrap { trim: false });
-    f.render_widget(p, area);
+    f.render_widget(Clear, popup);
+    f.render_widget(p, popup);
+}
+
+fn draw_account_picker(f: &mut Frame, app: &App, area: Rect) {
+    let items: Vec<ListItem> = app
+        .accounts
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            let unread: usize = a.unread.values().sum();
+            let mut style = Style::default();
+            if i == app.account_cursor {
+                style = app.cfg.theme.selection;
+            }
+            let marker = if i == app.active { "*" } else { " " };
+            let badge = if unread > 0 { format!(" ({unread})") } else { String::new() };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{marker} ")),
+                Span::styled(format!("{}{}", a.account, badge), style),
+            ]))
+        })
+        .collect();
+
+    let popup = centered_rect(40, 40, area);
+    let list = List::new(items).block(bordered(app).title("Accounts"));
+    f.render_widget(Clear, popup);
+    f.render_widget(list, popup);
+}
+
+fn draw_stories(f: &mut Frame, app: &App, area: Rect) {
+    let acct = app.acct();
+    let now = now_ms();
+    let items: Vec<ListItem> = acct
+        .stories
+        .iter()
+        .enumerate()
+        .map(|(i, st)| {
+            let mut style = Style::default();
+            if i == app.story_cursor {
+                style = app.cfg.theme.selection;
+            }
+            let mut who = contact_display(acct, &app.cfg, &st.author);
+            if let Some(gid) = &st.group_id {
+                let group = acct
+                    .targets
+                    .iter()
+                    .find(|t| t.kind == TargetKind::Group && &t.addr == gid)
+                    .map(|t| t.display.clone())
+                    .unwrap_or_else(|| "group".to_string());
+                who = format!("{who} in {group}");
+            }
+            let ago = format_duration(((now - st.timestamp_ms) / 1000).max(0));
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{who} "), style),
+                Span::styled(format!("{ago} ago  "), app.cfg.theme.dim),
+                Span::raw(story_snippet(st)),
+            ]))
+        })
+        .collect();
+
+    let popup = centered_rect(70, 60, area);
+    let title = format!("Stories ({})", acct.stories.len());
+    let list = List::new(items).block(bordered(app).title(title));
+    f.render_widget(Clear, popup);
+    f.render_widget(list, popup);
+}
+
+fn draw_sticker_picker(f: &mut Frame, app: &App, area: Rect) {
+    let packs = app.acct().sticker_packs.as_deref().unwrap_or_default();
+    let popup = centered_rect(50, 60, area);
+    // Keep the cursor on screen without a ListState: show a window around it.
+    let visible = popup.height.saturating_sub(2).max(1) as usize;
+    let first = app.sticker_cursor.saturating_sub(visible - 1);
+    let items: Vec<ListItem> = app
+        .sticker_choices
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .map(|(i, st)| {
+            let mut style = Style::default();
+            if i == app.sticker_cursor {
+                style = app.cfg.theme.selection;
+            }
+            let title = packs
+                .iter()
+                .find(|p| p.pack_id == st.pack_id)
+                .and_then(|p| p.title.clone())
+                .unwrap_or_else(|| st.pack_id.clone());
+            let emoji = st.emoji.clone().unwrap_or_else(|| "?".to_string());
+            ListItem::new(Line::from(vec![Span::styled(
+                format!("{emoji}  {title} #{}", st.sticker_id),
+                style,
+            )]))
+        })
+        .collect();
+
+    let title = format!("Stickers ({})", app.sticker_choices.len());
+    let list = List::new(items).block(bordered(app).title(title));
+    f.render_widget(Clear, popup);
+    f.render_widget(list, popup);
+}
+
+fn draw_finder(f: &mut Frame, app: &App, area: Rect) {
+    let acct = app.acct();
+    let popup = centered_rect(60, 60, area);
+    let block = bordered(app).title(format!("Find chat ({}/{})", app.finder_matches.len(), acct.targets.len()));
+    let inner = block.inner(popup);
+    f.render_widget(Clear, popup);
+    f.render_widget(block, popup);
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(inner);
+
+    // The query is one line; pan sideways like the composer.
+    let (_, col) = app.input.cursor_pos();
+    let width = parts[0].width.max(3) as usize;
+    let col_off = (col + 2).saturating_sub(width - 1);
+    let query = Paragraph::new(Line::from(vec![
+        Span::styled("> ", app.cfg.theme.status),
+        Span::raw(app.input.text().to_string()),
+    ]))
+    .scroll((0, col_off as u16));
+    f.render_widget(query, parts[0]);
+    f.set_cursor_position((parts[0].x + (col + 2 - col_off) as u16, parts[0].y));
+
+    let visible = parts[1].height.max(1) as usize;
+    let first = app.finder_cursor.saturating_sub(visible - 1);
+    let items: Vec<ListItem> = app
+        .finder_matches
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .filter_map(|(row, key)| {
+            let t = acct.targets.iter().find(|t| &t.conversation_key == key)?;
+            let prefix = match t.kind {
+                TargetKind::Contact => "@",
+                TargetKind::Group => "#",
+            };
+            let style = if row == app.finder_cursor { app.cfg.theme.selection } else { Style::default() };
+            let mut spans = vec![Span::styled(format!("{prefix} {}", t.display), style)];
+            let dim = if row == app.finder_cursor { style } else { app.cfg.theme.dim };
+            if t.kind == TargetKind::Contact && t.display != t.addr {
+                spans.push(Span::styled(format!("  {}", t.addr), dim));
+            }
+            if acct.archived.contains(&t.conversation_key) {
+                spans.push(Span::styled(" [archived]", dim));
+            }
+            Some(ListItem::new(Line::from(spans)))
+        })
+        .collect();
+    f.render_widget(List::new(items), parts[1]);
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let v = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(v[1])[1]
+}
+
+fn format_safety_number(sn: &str) -> Vec<String> {
+    // Signal shows 12 blocks of 5 digits, 4 per row.
+    let digits: Vec<char> = sn.chars().filter(|c| c.is_ascii_digit()).collect();
+    let blocks: Vec<String> = digits.chunks(5).map(|c| c.iter().collect()).collect();
+    blocks.chunks(4).map(|row| row.join(" ")).collect()
 }
 
 fn draw_status(f: &mut Frame, app: &App, area: Rect) {
+    let keys = &app.cfg.keys;
     let help = match app.mode {
-        Mode::Normal => "normal: j/k move, i insert, a add-recipient, r sync, q quit",
-        Mode::Insert => "insert: type, Enter send, Esc cancel",
-        Mode::AddRecipient => "add-recipient: type +E164, Enter add, Esc cancel",
+        Mode::Normal => format!(
+            "normal: {}",
+            keys.help(
+                KeyMode::Normal,
+                &[
+                    ("down", "down"),
+                    ("up", "up"),
+                    ("find", "find"),
+                    ("insert", "insert"),
+                    ("add", "add-recipient"),
+                    ("verify", "verify"),
+                    ("sync", "sync"),
+                    ("command-line", "command"),
+                    ("quit", "quit"),
+                ],
+            )
+        ),
+        Mode::Insert => format!(
+            "insert: type (*bold* _italic_ ~strike~ `mono` ||spoiler||), {}",
+            keys.help(
+                KeyMode::Insert,
+                &[("send", "send"), ("newline", "newline"), ("edit", "$EDITOR"), ("cancel", "cancel")],
+            )
+        ),
+        Mode::AddRecipient => format!(
+            "add-recipient: type +E164, {}",
+            keys.help(KeyMode::Prompt, &[("accept", "add"), ("cancel", "cancel")])
+        ),
+        Mode::Identity => format!(
+            "identity: {}",
+            keys.help(KeyMode::Popup, &[("trust", "mark verified"), ("cancel", "close")])
+        ),
+        Mode::AccountPicker => format!("accounts: {}", popup_help(keys, "switch", "cancel")),
+        Mode::SetTimer => format!(
+            "timer: type 30s/5m/8h/1d/4w/off, {}",
+            keys.help(KeyMode::Prompt, &[("accept", "set"), ("cancel", "cancel")])
+        ),
+        Mode::Command => command_hint(app),
+        Mode::StickerPicker => format!("stickers: {}", popup_help(keys, "send", "cancel")),
+        Mode::Stories => format!(
+            "stories: {}",
+            keys.help(
+                KeyMode::Popup,
+                &[("down", "down"), ("up", "up"), ("reply", "reply"), ("accept", "reply"), ("cancel", "close")]
+            )
+        ),
+        Mode::Finder => format!(
+            "find: type to filter, {}",
+            keys.help(KeyMode::Finder, &[("down", "down"), ("up", "up"), ("accept", "open"), ("cancel", "cancel")])
+        ),
     };
 
-    let l1 = Line::from(vec![
-        Span::styled(&app.account, Style::default().fg(Color::Cyan)),
-        Span::raw("  "),
-        Span::raw(help),
-    ]);
+    let mut l1 = vec![Span::styled(&app.acct().account, app.cfg.theme.status)];
+    if app.accounts.len() > 1 {
+        let elsewhere: usize = app
+            .accounts
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != app.active)
+            .flat_map(|(_, a)| a.unread.values())
+            .sum();
+        l1.push(Span::raw(format!(" ({}/{})", app.active + 1, app.accounts.len())));
+        if elsewhere > 0 {
+            l1.push(Span::styled(
+                format!(" +{elsewhere} unread elsewhere"),
+                app.cfg.theme.unread,
+            ));
+        }
+    }
+    l1.push(Span::raw("  "));
+    match &app.history_search {
+        Some(s) => l1.push(Span::styled(
+            format!("(reverse-i-search) '{}': Ctrl-r older, Enter accept, Esc cancel", s.query),
+            app.cfg.theme.highlight,
+        )),
+        None => l1.push(Span::raw(help)),
+    }
+
+    let block = bordered(app).title("Status");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1)])
+        .split(inner);
+    f.render_widget(Paragraph::new(Line::from(l1)), parts[0]);
+
+    match app.mode {
+        Mode::Insert | Mode::AddRecipient | Mode::SetTimer | Mode::Command => draw_input(f, app, parts[1]),
+        Mode::Normal
+        | Mode::Identity
+        | Mode::AccountPicker
+        | Mode::StickerPicker
+        | Mode::Stories
+        | Mode::Finder => {
+            let p = Paragraph::new(app.status.clone()).wrap(Wrap { trim: true });
+            f.render_widget(p, parts[1]);
+        }
+    }
+}
+
+// The composer grows with its content up to this many lines, then scrolls.
+const MAX_INPUT_LINES: usize = 8;
 
-    let l2 = match app.mode {
-        Mode::Insert | Mode::AddRecipient => Line::from(vec![
-            Span::styled("> ", Style::default().fg(Color::Yellow)),
-            Span::raw(app.input.clone()),
-        ]),
-        Mode::Normal => Line::from(vec![Span::raw(app.status.clone())]),
+fn status_height(app: &App) -> u16 {
+    let rows = if app.mode == Mode::Insert {
+        app.input.lines().len().clamp(1, MAX_INPUT_LINES)
+    } else {
+        1
     };
+    // Borders + help line + input/status rows.
+    rows as u16 + 3
+}
+
+fn draw_input(f: &mut Frame, app: &App, area: Rect) {
+    const PREFIX: usize = 2;
+    let first = if app.mode == Mode::Command { ": " } else { "> " };
+    let lines: Vec<Line> = app
+        .input
+        .lines()
+        .into_iter()
+        .enumerate()
+        .map(|(i, l)| {
+            let prefix = if i == 0 { first } else { "  " };
+            Line::from(vec![
+                Span::styled(prefix, app.cfg.theme.status),
+                Span::raw(l.to_string()),
+            ])
+        })
+        .collect();
 
-    let p = Paragraph::new(vec![l1, l2])
-        .block(Block::default().borders(Borders::ALL).title("Status"))
-        .wrap(Wrap { trim: true });
+    // Scroll so the cursor stays visible; no wrapping, long lines pan sideways.
+    let (row, col) = app.input.cursor_pos();
+    let height = area.height.max(1) as usize;
+    let width = area.width.max(1) as usize;
+    let row_off = row.saturating_sub(height - 1);
+    let col_off = (col + PREFIX).saturating_sub(width - 1);
+    let p = Paragraph::new(lines).scroll((row_off as u16, col_off as u16));
     f.render_widget(p, area);
+    f.set_cursor_position((
+        area.x + (PREFIX + col - col_off) as u16,
+        area.y + (row - row_off) as u16,
+    ));
 }
 
 fn mark_selected_read(app: &mut App) {
-    let key = app.selected_target().map(|t| t.conversation_key.clone());
-    if let Some(k) = key {
-        if app.unread.remove(&k).is_some() {
-            app.title_dirty = true;
+    let Some(key) = app.selected_target().map(|t| t.conversation_key.clone()) else { return; };
+    if app.acct_mut().unread.remove(&key).is_some() {
+        app.title_dirty = true;
+        // The chat drops out of the unread ones at the top.
+        let view = app.chat_view();
+        if view.sort == config::ChatSort::Unread {
+            sort_targets(app.acct_mut(), view);
         }
     }
+    start_timers(app, &key);
+}
+
+/// Starts the disappearing timers of a chat's messages that were not read yet.
+fn start_timers(app: &mut App, conversation_key: &str) {
+    let now = now_ms();
+    let mut started = false;
+    let acct = app.acct_mut();
+    for m in acct.messages.get_mut(conversation_key).into_iter().flatten() {
+        if m.expires_in_secs > 0 && m.expires_at_ms.is_none() {
+            m.expires_at_ms = Some(now + i64::from(m.expires_in_secs) * 1000);
+            started = true;
+        }
+    }
+    if started && app.cfg.save_scrollback {
+        let _ = scrollback::start_timers(&app.cfg.scrollback_dir, &app.acct().account, conversation_key, now);
+    }
 }
 
 fn update_title(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &App) {
-    let unread_total: usize = app.unread.values().sum();
+    // Combined over all accounts; the background receivers run for each of them.
+    // Muted chats keep their own counters but stay out of the title.
+    let now = now_ms();
+    let unread_total: usize = app
+        .accounts
+        .iter()
+        .flat_map(|a| {
+            a.unread
+                .iter()
+                .filter(move |(k, _)| !chat_rule(a, &app.cfg, k).muted(now))
+                .map(|(_, n)| n)
+        })
+        .sum();
     let title = if unread_total > 0 {
         format!("signal-tui ({unread_total})")
     } else {
@@ -661,73 +3269,186 @@ fn update_title(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &App
     let _ = execute!(terminal.backend_mut(), SetTitle(title));
 }
 
-fn notify_send_available() -> bool {
-    let has_display =
-        std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some();
-    if !has_display {
-        return false;
-    }
-    if let Some(path) = std::env::var_os("PATH") {
-        for p in std::env::split_paths(&path) {
-            if p.join("notify-send").exists() {
-                return true;
-            }
-        }
-    }
-    false
-}
-
-fn notify_incoming(app: &App, conversation_key: &str, source: Option<&str>, body: &str) {
-    let chat = app
-        .targets
+fn chat_name<'a>(acct: &'a AccountState, conversation_key: &'a str) -> &'a str {
+    acct.targets
         .iter()
         .find(|t| t.conversation_key == conversation_key)
         .map(|t| t.display.as_str())
-        .unwrap_or(conversation_key);
-    let from = source.unwrap_or("unknown");
+        .unwrap_or(conversation_key)
+}
 
-    let mut msg = body.to_string();
-    if msg.len() > 200 {
-        msg.truncate(200);
+fn notify_incoming(
+    notifier: &mut notify::Notifier,
+    acct: &AccountState,
+    cfg: &config::Config,
+    conversation_key: &str,
+    rule: &ChatRule,
+    source: Option<&str>,
+    body: &str,
+) -> Result<()> {
+    let chat = chat_name(acct, conversation_key);
+    let title = rule.title.clone().unwrap_or_else(|| format!("Signal: {chat}"));
+    let from = source.map(|n| contact_display(acct, cfg, n)).unwrap_or_else(|| "unknown".to_string());
+
+    let mut msg: String = body.chars().take(200).collect();
+    if msg.len() < body.len() {
         msg.push_str("...");
     }
+    notifier.show(&acct.account, conversation_key, &title, &format!("{from}: {msg}"))
+}
 
-    let _ = std::process::Command::new("notify-send")
-        .args([
-            "-a",
-            "signal-tui",
-            "-t",
-            "4000",
-            &format!("Signal: {chat}"),
-            &format!("{from}: {msg}"),
-        ])
-        .spawn();
+/// A notification's Open/Reply action: brings up its chat, in insert mode for Reply.
+fn focus_chat(app: &mut App, account: &str, conversation_key: &str, reply: bool) {
+    let Some(idx) = app.accounts.iter().position(|a| a.account == account) else { return; };
+    // Leave whatever was open the way Esc would.
+    if app.mode == Mode::Insert {
+        stash_draft(app);
+        app.story_reply = None;
+    }
+    app.input.clear();
+    app.history_nav = None;
+    app.history_search = None;
+    app.pending_keys.clear();
+    app.mode = Mode::Normal;
+    if idx != app.active {
+        switch_account(app, idx);
+    }
+    select_chat(app, conversation_key);
+    if reply {
+        app.mode = Mode::Insert;
+        restore_draft(app);
+    }
 }
 
-fn load_initial_scrollback(app: &mut App) -> Result<()> {
-    for t in &app.targets {
+fn load_initial_scrollback(cfg: &config::Config, acct: &mut AccountState) -> Result<()> {
+    let now = now_ms();
+    for t in &acct.targets {
+        // Expired while we were not running; the whole file, not just the tail.
+        let _ = scrollback::purge_expired(&cfg.scrollback_dir, &acct.account, &t.conversation_key, now);
         let recs = scrollback::load_tail(
-            &app.cfg.scrollback_dir,
+            &cfg.scrollback_dir,
+            &acct.account,
             &t.conversation_key,
-            app.cfg.scrollback_load_limit,
+            cfg.scrollback_load_limit,
         )?;
         if recs.is_empty() {
             continue;
         }
-        let v = app.messages.entry(t.conversation_key.clone()).or_default();
+        // Sent messages of older versions carry no timestamp; fall back to when
+        // the file was written.
+        let last = recs
+            .iter()
+            .filter_map(|r| r.ts_ms)
+            .max()
+            .or_else(|| scrollback::last_modified_ms(&cfg.scrollback_dir, &acct.account, &t.conversation_key));
+        if let Some(ts) = last {
+            acct.last_activity.insert(t.conversation_key.clone(), ts);
+        }
+        let v = acct.messages.entry(t.conversation_key.clone()).or_default();
         for r in recs {
             v.push(ChatMessage {
                 ts_ms: r.ts_ms,
-                dir: if r.dir == "out" { MsgDir::Out } else { MsgDir::In },
+                dir: match r.dir.as_str() {
+                    "out" => MsgDir::Out,
+                    "system" => MsgDir::System,
+                    _ => MsgDir::In,
+                },
                 who: r.who,
                 body: r.body,
+                expires_in_secs: r.expires_in_secs.unwrap_or(0),
+                expires_at_ms: r.expires_at_ms,
+                view_once: r.view_once_paths.into_iter().map(PathBuf::from).filter(|p| p.exists()).collect(),
+                styles: r.text_styles,
+                sticker: r.sticker,
             });
         }
     }
     Ok(())
 }
 
-fn print_help() {
+/// Remembers `key`'s disappearing timer, persisting the account's timers when it changed.
+fn record_timer(cfg: &config::Config, acct: &mut AccountState, key: &str, secs: u32) -> Result<()> {
+    let old = if secs == 0 {
+        acct.timers.remove(key)
+    } else {
+        acct.timers.insert(key.to_string(), secs)
+    };
+    if old.unwrap_or(0) == secs {
+        return Ok(());
+    }
+    state::save_timers(&cfg.state_dir, &acct.account, &acct.timers)?;
+    if secs > 0 && acct.drafts.contains_key(key) {
+        // The chat's draft now stays in memory only.
+        persist_drafts(cfg, acct)?;
+    }
+    Ok(())
+}
+
+/// Drops disappearing messages whose timer ran out, in memory and in scrollback.
+fn expire_messages(app: &mut App) {
+    let now = now_ms();
+    for acct in &mut app.accounts {
+        acct.stories.retain(|s| now - s.timestamp_ms < STORY_TTL_MS);
+        for (key, msgs) in acct.messages.iter_mut() {
+            let before = msgs.len();
+            msgs.retain(|m| m.expires_at_ms.is_none_or(|at| at > now));
+            if msgs.len() != before {
+                let _ = scrollback::purge_expired(&app.cfg.scrollback_dir, &acct.account, key, now);
+            }
+        }
+    }
+}
+
+const STORY_TTL_MS: i64 = 24 * 3600 * 1000;
+
+/// When a message whose timer starts now disappears.
+fn expiry_for(expires_in_secs: u32) -> Option<i64> {
+    if expires_in_secs == 0 {
+        return None;
+    }
+    Some(now_ms() + i64::from(expires_in_secs) * 1000)
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn format_duration(secs: i64) -> String {
+    match secs {
+        s if s < 60 => format!("{s}s"),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h", s / 3600),
+        s if s < 7 * 86_400 => format!("{}d", s / 86_400),
+        s => format!("{}w", s / (7 * 86_400)),
+    }
+}
+
+/// Parses "30s", "5m", "8h", "1d", "4w" (bare numbers are seconds); "off"/"0" disables.
+fn parse_timer(s: &str) -> Option<u32> {
+    let s = s.trim().to_lowercase();
+    if s == "off" || s == "0" {
+        return Some(0);
+    }
+    let (num, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s.as_str(), "s"),
+    };
+    let n: u32 = num.parse().ok()?;
+    let mult = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => return None,
+    };
+    n.checked_mul(mult)
+}
+
+fn print_help(keys: &Keymap) {
     println!(
         "signal-tui
 
@@ -736,16 +3457,83 @@ USAGE:
 
 FILES:
   Config:      $XDG_CONFIG_HOME/signal-tui/config.toml (default: ~/.config/signal-tui/config.toml)
-  Scrollback:  $XDG_STATE_HOME/signal-tui/scrollback (default: ~/.local/state/signal-tui/scrollback)
-
-KEYS:
-  j/k (or arrows)  Move
-  gg / G           Top / bottom
-  a                Add recipient (+E164)
-  i                Compose message
-  Enter            Send (insert mode)
-  Esc              Cancel
-  r                Sync once
-  q                Quit"
+  Scrollback:  $XDG_STATE_HOME/signal-tui/scrollback/<account> (default: ~/.local/state/signal-tui/scrollback/<account>)
+  Drafts:      $XDG_STATE_HOME/signal-tui/drafts.json
+  Pinned:      $XDG_STATE_HOME/signal-tui/pinned.json
+  Archived:    $XDG_STATE_HOME/signal-tui/archived.json
+  Mute etc.:   $XDG_STATE_HOME/signal-tui/notify_rules.json
+  History:     $XDG_STATE_HOME/signal-tui/history.json
+  Timers:      $XDG_STATE_HOME/signal-tui/timers.json
+"
+    );
+
+    // Generated from the active bindings ([keys] in the config).
+    println!("KEYS (normal mode):");
+    for (action, bound) in keys.actions(KeyMode::Normal) {
+        let what = match action.as_str() {
+            "command-line" => "command line (Tab completes, :help lists commands)".to_string(),
+            line => {
+                let (name, _) = commands::split(line);
+                match commands::find(name) {
+                    Ok(c) if c.name == line => c.help.to_string(),
+                    _ => format!(":{line}"),
+                }
+            }
+        };
+        println!("  {:<16} {what}", bound.join(" / "));
+    }
+    println!("\nKEYS (insert mode):");
+    for (action, bound) in keys.actions(KeyMode::Insert) {
+        let what = match action.as_str() {
+            "send" => "send",
+            "newline" => "new line",
+            "cancel" => "back to normal mode (text stays as the chat's draft)",
+            "edit" => "edit in $VISUAL/$EDITOR",
+            "history-search" => "search sent messages",
+            other => other,
+        };
+        println!("  {:<16} {what}", bound.join(" / "));
+    }
+    println!("\nKEYS (prompts and : command line):");
+    for (action, bound) in keys.actions(KeyMode::Prompt) {
+        let what = match action.as_str() {
+            "accept" => "accept",
+            "cancel" => "cancel",
+            "complete" => "complete (command line)",
+            "history-search" => "search earlier entries",
+            other => other,
+        };
+        println!("  {:<16} {what}", bound.join(" / "));
+    }
+    println!("\nKEYS (popups: accounts, stickers, stories, safety numbers):");
+    for (action, bound) in keys.actions(KeyMode::Popup) {
+        let what = match action.as_str() {
+            "down" => "next entry",
+            "up" => "previous entry",
+            "accept" => "choose",
+            "cancel" => "close",
+            "trust" => "mark the safety numbers verified",
+            "reply" => "reply to the story",
+            other => other,
+        };
+        println!("  {:<16} {what}", bound.join(" / "));
+    }
+    println!("\nKEYS (finder):");
+    for (action, bound) in keys.actions(KeyMode::Finder) {
+        let what = match action.as_str() {
+            "down" => "next match",
+            "up" => "previous match",
+            "accept" => "open the chat",
+            "cancel" => "cancel",
+            other => other,
+        };
+        println!("  {:<16} {what}", bound.join(" / "));
+    }
+    println!(
+        "
+EDITING (insert mode and prompts):
+  Up / Down        previous / next sent message or prompt entry
+  Ctrl-w / Ctrl-u  delete word / to line start (Ctrl-k to end, Ctrl-y paste)
+  Ctrl-c           quit (always)"
     );
 }
@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::thread;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use zbus::blocking::fdo::DBusProxy;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::Value;
+
+const SERVICE: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+// How long a notification stays up (ms); the server may ignore it.
+const TIMEOUT_MS: i32 = 4000;
+
+/// How long a chat's notifications are muted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mute {
+    Off,
+    Forever,
+    // Until this time (ms since the epoch).
+    Until(i64),
+}
+
+/// Notification settings of one chat. Unset fields fall back to the
+/// `[chats]` entry in the config file, then to the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChatRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mute: Option<Mute>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mentions_only: Option<bool>,
+    // Replaces "Signal: <chat>" as the notification title.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl ChatRule {
+    /// `self`, with the fields it leaves unset taken from `base`.
+    pub fn or(self, base: Option<&ChatRule>) -> ChatRule {
+        let Some(base) = base else { return self; };
+        ChatRule {
+            mute: self.mute.or(base.mute),
+            mentions_only: self.mentions_only.or(base.mentions_only),
+            title: self.title.or_else(|| base.title.clone()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == ChatRule::default()
+    }
+
+    pub fn muted(&self, now_ms: i64) -> bool {
+        match self.mute {
+            None | Some(Mute::Off) => false,
+            Some(Mute::Forever) => true,
+            Some(Mute::Until(at)) => now_ms < at,
+        }
+    }
+
+    /// Whether a message should raise a notification. `mentioned`: it
+    /// mentions us (or is otherwise meant for us, like a call).
+    pub fn notifies(&self, now_ms: i64, mentioned: bool) -> bool {
+        !self.muted(now_ms) && (mentioned || !self.mentions_only.unwrap_or(false))
+    }
+}
+
+/// Desktop notifications over the org.freedesktop.Notifications D-Bus
+/// interface (whatever bus DBUS_SESSION_BUS_ADDRESS points at). Sending
+/// happens on a worker thread, so a slow server never stalls the UI.
+pub struct Notifier {
+    proxy: Proxy<'static>,
+    requests: mpsc::Sender<Request>,
+    // What the worker sent, as (chat, notification id) or an error.
+    results: mpsc::Receiver<Result<(Chat, u32)>>,
+    // (account, conversation key) -> id of the chat's latest notification.
+    shown: HashMap<Chat, u32>,
+}
+
+// (account, conversation key)
+type Chat = (String, String);
+
+struct Request {
+    chat: Chat,
+    summary: String,
+    body: String,
+}
+
+impl Notifier {
+    /// Connects to the session bus; fails without a notification server.
+    pub fn connect() -> Result<Self> {
+        Self::connect_to(SERVICE)
+    }
+
+    fn connect_to(service: &'static str) -> Result<Self> {
+        let conn = Connection::session().context("connect to the D-Bus session bus")?;
+        let proxy = Proxy::new(&conn, service, PATH, SERVICE).context("notification service")?;
+        let caps: Vec<String> = proxy.call("GetCapabilities", &()).context("no notification server")?;
+        let worker = Worker {
+            proxy: proxy.clone(),
+            actions: caps.iter().any(|c| c == "actions"),
+            markup: caps.iter().any(|c| c == "body-markup"),
+            shown: HashMap::new(),
+        };
+        let (requests, rx) = mpsc::channel();
+        let (tx, results) = mpsc::channel();
+        // Ends once the Notifier (and with it the request channel) is dropped.
+        thread::spawn(move || worker.run(rx, tx));
+        Ok(Self { proxy, requests, results, shown: HashMap::new() })
+    }
+
+    /// Runs `on_action(notification id, action key)` on a background thread
+    /// whenever an action of a notification is invoked. Keys are "default"
+    /// (the notification or its "Open" button was clicked) and "reply".
+    pub fn listen(&self, on_action: impl Fn(u32, String) + Send + 'static) -> Result<()> {
+        let signals = self.proxy.receive_signal("ActionInvoked").context("subscribe to notification actions")?;
+        let bus = DBusProxy::new(self.proxy.connection()).context("connect to the D-Bus daemon")?;
+        let service = self.proxy.destination().to_owned();
+        // Any client on the bus can emit ActionInvoked; take only the server's,
+        // by its unique name (looked up again when the server restarts).
+        let mut server = bus.get_name_owner(service.clone()).ok();
+        // Blocks on the bus for the rest of the process; not joined on exit.
+        thread::spawn(move || {
+            for msg in signals {
+                let header = msg.header();
+                let Some(sender) = header.sender() else { continue };
+                if server.as_deref() != Some(sender) {
+                    server = bus.get_name_owner(service.clone()).ok();
+                    if server.as_deref() != Some(sender) {
+                        continue;
+                    }
+                }
+                if let Ok((id, action)) = msg.body().deserialize::<(u32, String)>() {
+                    on_action(id, action);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Queues a notification for a chat, replacing the chat's previous one.
+    pub fn show(&mut self, account: &str, conversation_key: &str, summary: &str, body: &str) -> Result<()> {
+        let req = Request {
+            chat: (account.to_string(), conversation_key.to_string()),
+            summary: summary.to_string(),
+            body: body.to_string(),
+        };
+        self.requests.send(req).ok().context("notification thread stopped")
+    }
+
+    /// Takes in what the worker did since the last call; returns the latest
+    /// error, if sending failed.
+    pub fn poll(&mut self) -> Option<anyhow::Error> {
+        let mut err = None;
+        while let Ok(res) = self.results.try_recv() {
+            match res {
+                Ok((chat, id)) => {
+                    self.shown.insert(chat, id);
+                }
+                Err(e) => err = Some(e),
+            }
+        }
+        err
+    }
+
+    /// The (account, conversation key) a notification id was shown for.
+    pub fn chat_of(&mut self, id: u32) -> Option<(String, String)> {
+        // An action only comes after Notify returned, so its id is queued by now.
+        let _ = self.poll();
+        self.shown.iter().find(|&(_, &shown)| shown == id).map(|(chat, _)| chat.clone())
+    }
+}
+
+struct Worker {
+    proxy: Proxy<'static>,
+    // The server shows action buttons.
+    actions: bool,
+    // The server reads the body as markup, so it needs escaping.
+    markup: bool,
+    // Latest id per chat, so the next notification replaces it instead of stacking.
+    shown: HashMap<Chat, u32>,
+}
+
+impl Worker {
+    fn run(mut self, requests: mpsc::Receiver<Request>, results: mpsc::Sender<Result<(Chat, u32)>>) {
+        for req in requests {
+            let res = self.send(&req).map(|id| (req.chat, id));
+            if results.send(res).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn send(&mut self, req: &Request) -> Result<u32> {
+        let replaces = self.shown.get(&req.chat).copied().unwrap_or(0);
+        let actions: &[&str] = if self.actions { &["default", "Open", "reply", "Reply"] } else { &[] };
+        let hints: HashMap<&str, Value> = HashMap::new();
+        let body = if self.markup {
+            req.body.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+        } else {
+            req.body.clone()
+        };
+        let id: u32 = self
+            .proxy
+            .call("Notify", &("signal-tui", replaces, "", &req.summary, body, actions, hints, TIMEOUT_MS))
+            .context("send notification")?;
+        self.shown.insert(req.chat.clone(), id);
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use zbus::interface;
+
+    use super::*;
+
+    #[test]
+    fn runtime_rule_wins_over_the_config_field_by_field() {
+        let config = ChatRule { mute: Some(Mute::Forever), mentions_only: Some(true), title: Some("Cfg".into()) };
+        let runtime = ChatRule { mute: Some(Mute::Off), ..ChatRule::default() };
+        let rule = runtime.or(Some(&config));
+        assert_eq!(rule.mute, Some(Mute::Off));
+        // Fields the runtime rule leaves unset come from the config.
+        assert_eq!(rule.mentions_only, Some(true));
+        assert_eq!(rule.title.as_deref(), Some("Cfg"));
+        assert_eq!(ChatRule::default().or(Some(&config)), config);
+        assert!(ChatRule::default().or(None).is_empty());
+    }
+
+    #[test]
+    fn timed_mutes_expire() {
+        let rule = ChatRule { mute: Some(Mute::Until(1_000)), ..ChatRule::default() };
+        assert!(rule.muted(999));
+        assert!(!rule.notifies(999, true));
+        assert!(!rule.muted(1_000));
+        assert!(rule.notifies(1_000, false));
+        let forever = ChatRule { mute: Some(Mute::Forever), ..ChatRule::default() };
+        assert!(forever.muted(i64::MAX));
+    }
+
+    #[test]
+    fn mentions_only_needs_a_mention() {
+        let rule = ChatRule { mentions_only: Some(true), ..ChatRule::default() };
+        assert!(!rule.notifies(0, false));
+        assert!(rule.notifies(0, true));
+        assert!(ChatRule::default().notifies(0, false));
+    }
+
+    // Not the real name, so a desktop's notification server is left alone.
+    const TEST_SERVICE: &str = "org.signaltui.TestNotifications";
+
+    struct Stub {
+        next_id: u32,
+        // (replaces_id, returned id) per Notify call.
+        calls: Arc<Mutex<Vec<(u32, u32)>>>,
+    }
+
+    #[interface(name = "org.freedesktop.Notifications")]
+    impl Stub {
+        fn get_capabilities(&self) -> Vec<&str> {
+            vec!["actions", "body"]
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &mut self,
+            _app_name: &str,
+            replaces_id: u32,
+            _app_icon: &str,
+            _summary: &str,
+            _body: &str,
+            _actions: Vec<&str>,
+            _hints: HashMap<&str, Value<'_>>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let id = if replaces_id != 0 {
+                replaces_id
+            } else {
+                self.next_id += 1;
+                self.next_id
+            };
+            self.calls.lock().unwrap().push((replaces_id, id));
+            id
+        }
+    }
+
+    fn wait_for(n: &mut Notifier, id: u32) -> (String, String) {
+        let start = Instant::now();
+        loop {
+            if let Some(chat) = n.chat_of(id) {
+                return chat;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "notification {id} never shown");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a session bus: dbus-run-session -- cargo test -- --ignored"]
+    fn replaces_per_chat_and_routes_actions() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let server = zbus::blocking::connection::Builder::session()
+            .unwrap()
+            .name(TEST_SERVICE)
+            .unwrap()
+            .serve_at(PATH, Stub { next_id: 0, calls: calls.clone() })
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut n = Notifier::connect_to(TEST_SERVICE).unwrap();
+        let (tx, rx) = mpsc::channel();
+        n.listen(move |id, action| {
+            let _ = tx.send((id, action));
+        })
+        .unwrap();
+
+        let a = ("+15550000001".to_string(), "contact:+15550000002".to_string());
+        let b = ("+15550000001".to_string(), "group:abc".to_string());
+        n.show(&a.0, &a.1, "Signal: A", "one").unwrap();
+        assert_eq!(wait_for(&mut n, 1), a);
+        n.show(&a.0, &a.1, "Signal: A", "two").unwrap();
+        n.show(&b.0, &b.1, "Signal: B", "three").unwrap();
+        assert_eq!(wait_for(&mut n, 2), b);
+        assert!(n.poll().is_none());
+
+        // The second message of chat A replaces its first notification.
+        assert_eq!(*calls.lock().unwrap(), [(0, 1), (1, 1), (0, 2)]);
+
+        // Another client's signal is not the server's and is dropped.
+        let other = Connection::session().unwrap();
+        other.emit_signal(None::<()>, PATH, SERVICE, "ActionInvoked", &(1u32, "default")).unwrap();
+        server
+            .emit_signal(None::<()>, PATH, SERVICE, "ActionInvoked", &(2u32, "reply"))
+            .unwrap();
+        let (id, action) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!((id, action.as_str()), (2, "reply"));
+        assert_eq!(n.chat_of(id), Some(b));
+    }
+}
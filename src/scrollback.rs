@@ -5,18 +5,41 @@ use std::path::{Path, PathBuf};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
+use crate::signal_cli::Sticker;
+use crate::state;
+use crate::text_style::StyleRange;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScrollbackRecord {
     pub ts_ms: Option<i64>,
-    pub dir: String, // "in" | "out"
+    pub dir: String, // "in" | "out" | "system"
     pub who: Option<String>,
     pub body: String,
+    // Disappearing messages: the chat's timer and when this message goes away.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_in_secs: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at_ms: Option<i64>,
+    // View-once media not opened yet, one path per attachment; cleared (and
+    // the files deleted) once viewed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub view_once_paths: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub text_styles: Vec<StyleRange>,
+    // Which sticker `body` labels, so its image can be shown again later.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sticker: Option<Sticker>,
 }
 
-pub fn append(scrollback_dir: &Path, conversation_key: &str, rec: &ScrollbackRecord) -> Result<()> {
-    fs::create_dir_all(scrollback_dir)
-        .with_context(|| format!("create scrollback dir {scrollback_dir:?}"))?;
-    let path = path_for(scrollback_dir, conversation_key);
+pub fn append(
+    scrollback_dir: &Path,
+    account: &str,
+    conversation_key: &str,
+    rec: &ScrollbackRecord,
+) -> Result<()> {
+    let dir = account_dir(scrollback_dir, account);
+    fs::create_dir_all(&dir).with_context(|| format!("create scrollback dir {dir:?}"))?;
+    let path = path_for(scrollback_dir, account, conversation_key);
     let mut f = OpenOptions::new()
         .create(true)
         .append(true)
@@ -31,10 +54,11 @@ pub fn append(scrollback_dir: &Path, conversation_key: &str, rec: &ScrollbackRec
 
 pub fn load_tail(
     scrollback_dir: &Path,
+    account: &str,
     conversation_key: &str,
     limit: usize,
 ) -> Result<Vec<ScrollbackRecord>> {
-    let path = path_for(scrollback_dir, conversation_key);
+    let path = path_for(scrollback_dir, account, conversation_key);
     if !path.exists() {
         return Ok(vec![]);
     }
@@ -63,9 +87,195 @@ pub fn load_tail(
     Ok(buf)
 }
 
-fn path_for(scrollback_dir: &Path, conversation_key: &str) -> PathBuf {
+/// Rewrites a conversation's scrollback without records that expired before `now_ms`.
+/// Returns how many records were dropped.
+pub fn purge_expired(
+    scrollback_dir: &Path,
+    account: &str,
+    conversation_key: &str,
+    now_ms: i64,
+) -> Result<usize> {
+    let mut dropped = 0;
+    rewrite(scrollback_dir, account, conversation_key, |r| {
+        if r.expires_at_ms.is_some_and(|at| at <= now_ms) {
+            dropped += 1;
+            None
+        } else {
+            Some(r)
+        }
+    })?;
+    Ok(dropped)
+}
+
+/// Starts the timers of disappearing records that were still unread (a timer
+/// but no expiry yet), counting from `now_ms`.
+pub fn start_timers(
+    scrollback_dir: &Path,
+    account: &str,
+    conversation_key: &str,
+    now_ms: i64,
+) -> Result<()> {
+    rewrite(scrollback_dir, account, conversation_key, |mut r| {
+        if let Some(secs) = r.expires_in_secs
+            && r.expires_at_ms.is_none()
+        {
+            r.expires_at_ms = Some(now_ms + i64::from(secs) * 1000);
+        }
+        Some(r)
+    })?;
+    Ok(())
+}
+
+/// Replaces the view-once record pointing at `media_paths` with a "viewed" stub.
+pub fn mark_viewed(
+    scrollback_dir: &Path,
+    account: &str,
+    conversation_key: &str,
+    media_paths: &[String],
+    stub: &str,
+) -> Result<()> {
+    rewrite(scrollback_dir, account, conversation_key, |mut r| {
+        if r.view_once_paths == media_paths {
+            r.view_once_paths.clear();
+            r.body = stub.to_string();
+        }
+        Some(r)
+    })?;
+    Ok(())
+}
+
+/// Runs every record through `f` (None drops it) and atomically replaces the file
+/// if anything changed. Lines that do not parse are kept as they are.
+fn rewrite(
+    scrollback_dir: &Path,
+    account: &str,
+    conversation_key: &str,
+    mut f: impl FnMut(ScrollbackRecord) -> Option<ScrollbackRecord>,
+) -> Result<()> {
+    let path = path_for(scrollback_dir, account, conversation_key);
+    if !path.exists() {
+        return Ok(());
+    }
+    let raw = fs::read_to_string(&path).with_context(|| format!("read scrollback {path:?}"))?;
+    let mut out = String::with_capacity(raw.len());
+    let mut changed = false;
+    for line in raw.lines() {
+        let Ok(rec) = serde_json::from_str::<ScrollbackRecord>(line.trim()) else {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        };
+        let before = serde_json::to_string(&rec).context("serialize scrollback record")?;
+        match f(rec) {
+            Some(rec) => {
+                let after = serde_json::to_string(&rec).context("serialize scrollback record")?;
+                if after != before {
+                    changed = true;
+                    out.push_str(&after);
+                } else {
+                    out.push_str(line);
+                }
+                out.push('\n');
+            }
+            None => changed = true,
+        }
+    }
+    if !changed {
+        return Ok(());
+    }
+    // Write-then-rename so a crash never leaves a half-written history.
+    state::write_private(&path, out.as_bytes())
+}
+
+/// When the conversation's scrollback file was last written, in ms since the epoch.
+pub fn last_modified_ms(scrollback_dir: &Path, account: &str, conversation_key: &str) -> Option<i64> {
+    let modified = fs::metadata(path_for(scrollback_dir, account, conversation_key))
+        .and_then(|m| m.modified())
+        .ok()?;
+    let since = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(since.as_millis() as i64)
+}
+
+/// Moves scrollback files from the old flat layout (`<dir>/<key>.jsonl`) into
+/// `account`'s directory. Safe to run on every start; returns how many files moved.
+pub fn migrate_flat_layout(scrollback_dir: &Path, account: &str) -> Result<usize> {
+    let entries = match fs::read_dir(scrollback_dir) {
+        Ok(e) => e,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).with_context(|| format!("read scrollback dir {scrollback_dir:?}")),
+    };
+    let dest_dir = account_dir(scrollback_dir, account);
+    let mut moved = 0;
+    for entry in entries {
+        let entry = entry.context("read scrollback dir entry")?;
+        let src = entry.path();
+        if !src.is_file() || src.extension().is_none_or(|e| e != "jsonl") {
+            continue;
+        }
+        fs::create_dir_all(&dest_dir)
+            .with_context(|| format!("create scrollback dir {dest_dir:?}"))?;
+        let dest = dest_dir.join(entry.file_name());
+        if dest.exists() {
+            // Both layouts have this chat (e.g. an older build ran again): keep both halves.
+            let old = fs::read_to_string(&src).with_context(|| format!("read scrollback {src:?}"))?;
+            let new = fs::read_to_string(&dest).with_context(|| format!("read scrollback {dest:?}"))?;
+            state::write_private(&dest, merge_by_time(&old, &new).as_bytes())?;
+            fs::remove_file(&src).with_context(|| format!("remove scrollback {src:?}"))?;
+        } else {
+            fs::rename(&src, &dest).with_context(|| format!("move {src:?} to {dest:?}"))?;
+        }
+        moved += 1;
+    }
+    Ok(moved)
+}
+
+/// Interleaves two scrollback files by timestamp, keeping each file's own
+/// order. Lines without one (sent messages, unparsable lines) stay right after
+/// the line before them; on equal times `first` goes first.
+fn merge_by_time(first: &str, second: &str) -> String {
+    fn timed(raw: &str) -> Vec<(i64, &str)> {
+        let mut last = i64::MIN;
+        raw.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| {
+                if let Some(ts) = serde_json::from_str::<ScrollbackRecord>(l.trim()).ok().and_then(|r| r.ts_ms) {
+                    last = last.max(ts);
+                }
+                (last, l)
+            })
+            .collect()
+    }
+    let (a, b) = (timed(first), timed(second));
+    let (mut i, mut j) = (0, 0);
+    let mut out = String::with_capacity(first.len() + second.len());
+    while i < a.len() || j < b.len() {
+        let line = if j == b.len() || (i < a.len() && a[i].0 <= b[j].0) {
+            i += 1;
+            a[i - 1].1
+        } else {
+            j += 1;
+            b[j - 1].1
+        };
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+fn account_dir(scrollback_dir: &Path, account: &str) -> PathBuf {
+    // Account ids are E.164 numbers; anything else gets hex-encoded to stay path-safe.
+    let safe = !account.is_empty()
+        && account.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-');
+    if safe {
+        scrollback_dir.join(account)
+    } else {
+        scrollback_dir.join(hex_encode(account.as_bytes()))
+    }
+}
+
+fn path_for(scrollback_dir: &Path, account: &str, conversation_key: &str) -> PathBuf {
     let hex = hex_encode(conversation_key.as_bytes());
-    scrollback_dir.join(format!("{hex}.jsonl"))
+    account_dir(scrollback_dir, account).join(format!("{hex}.jsonl"))
 }
 
 fn hex_encode(bytes: &[u8]) -> String {
@@ -77,3 +287,108 @@ fn hex_encode(bytes: &[u8]) -> String {
     }
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(ts: Option<i64>, body: &str) -> String {
+        serde_json::to_string(&record(ts, body)).unwrap() + "\n"
+    }
+
+    fn record(ts: Option<i64>, body: &str) -> ScrollbackRecord {
+        ScrollbackRecord {
+            ts_ms: ts,
+            dir: if ts.is_some() { "in" } else { "out" }.to_string(),
+            who: None,
+            body: body.to_string(),
+            expires_in_secs: None,
+            expires_at_ms: None,
+            view_once_paths: Vec::new(),
+            text_styles: Vec::new(),
+            sticker: None,
+        }
+    }
+
+    fn bodies(raw: &str) -> Vec<String> {
+        raw.lines().map(|l| serde_json::from_str::<ScrollbackRecord>(l).unwrap().body).collect()
+    }
+
+    #[test]
+    fn merge_interleaves_by_time() {
+        let legacy = [line(Some(1), "a1"), line(None, "a1 reply"), line(Some(5), "a5")].concat();
+        let newer = [line(Some(3), "b3"), line(Some(5), "b5"), line(None, "b5 reply")].concat();
+        assert_eq!(
+            bodies(&merge_by_time(&legacy, &newer)),
+            ["a1", "a1 reply", "b3", "a5", "b5", "b5 reply"]
+        );
+    }
+
+    #[test]
+    fn merge_keeps_unparsable_lines_in_place() {
+        let legacy = [line(Some(2), "a2"), "not json\n".to_string()].concat();
+        let newer = line(Some(1), "b1");
+        let merged = merge_by_time(&legacy, &newer);
+        assert_eq!(merged.lines().collect::<Vec<_>>()[1..], [line(Some(2), "a2").trim_end(), "not json"]);
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("signal-tui-scrollback-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read_bodies(dir: &Path, account: &str, key: &str) -> Vec<String> {
+        load_tail(dir, account, key, usize::MAX).unwrap().into_iter().map(|r| r.body).collect()
+    }
+
+    #[test]
+    fn migration_moves_flat_files_once_and_merges_duplicates() {
+        let dir = temp_dir("migrate");
+        let account = "+15550000001";
+        let flat = |key: &str| dir.join(format!("{}.jsonl", hex_encode(key.as_bytes())));
+        fs::write(flat("contact:+1"), [line(Some(1), "old 1"), line(Some(2), "old 2")].concat()).unwrap();
+        // This chat is in both layouts.
+        fs::write(flat("group:abc"), [line(Some(1), "flat 1"), line(Some(4), "flat 4")].concat()).unwrap();
+        append(&dir, account, "group:abc", &record(Some(3), "new 3")).unwrap();
+        fs::write(dir.join("notes.txt"), "not scrollback").unwrap();
+
+        assert_eq!(migrate_flat_layout(&dir, account).unwrap(), 2);
+        assert!(!flat("contact:+1").exists() && !flat("group:abc").exists());
+        assert!(dir.join("notes.txt").exists());
+        assert_eq!(read_bodies(&dir, account, "contact:+1"), ["old 1", "old 2"]);
+        assert_eq!(read_bodies(&dir, account, "group:abc"), ["flat 1", "new 3", "flat 4"]);
+
+        // Nothing left to move; the account's files stay as they are.
+        assert_eq!(migrate_flat_layout(&dir, account).unwrap(), 0);
+        assert_eq!(read_bodies(&dir, account, "contact:+1"), ["old 1", "old 2"]);
+        assert_eq!(read_bodies(&dir, account, "group:abc"), ["flat 1", "new 3", "flat 4"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn migration_without_a_scrollback_dir_does_nothing() {
+        let dir = std::env::temp_dir().join(format!("signal-tui-scrollback-none-{}", std::process::id()));
+        assert_eq!(migrate_flat_layout(&dir, "+15550000001").unwrap(), 0);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn rewrites_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("rewrite");
+        let account = "+15550000001";
+        let mut gone = record(Some(1), "gone");
+        gone.expires_at_ms = Some(10);
+        append(&dir, account, "contact:+1", &gone).unwrap();
+        append(&dir, account, "contact:+1", &record(Some(2), "kept")).unwrap();
+        assert_eq!(purge_expired(&dir, account, "contact:+1", 10).unwrap(), 1);
+        assert_eq!(read_bodies(&dir, account, "contact:+1"), ["kept"]);
+        let mode = fs::metadata(path_for(&dir, account, "contact:+1")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
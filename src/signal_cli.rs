@@ -1,9 +1,15 @@
-use std::process::Command;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
 
 use anyhow::{Context, Result, bail};
-use serde::Deserialize;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::text_style::StyleRange;
+
 #[derive(Debug, Clone)]
 pub struct Contact {
     pub number: String,
@@ -47,6 +53,146 @@ pub struct IncomingMessage {
     pub source: Option<String>,
     pub timestamp_ms: Option<i64>,
     pub body: String,
+    pub expires_in_secs: u32,
+    pub view_once: bool,
+    pub attachments: Vec<Attachment>,
+    pub sticker: Option<Sticker>,
+    pub text_styles: Vec<StyleRange>,
+    // Numbers and UUIDs @-mentioned in the body.
+    pub mentions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sticker {
+    pub pack_id: String,
+    pub sticker_id: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StickerPack {
+    pub pack_id: String,
+    pub title: Option<String>,
+    pub installed: bool,
+    pub stickers: Vec<StickerInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StickerInfo {
+    pub id: u32,
+    pub emoji: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StickerPackJson {
+    #[serde(rename = "packId")]
+    pack_id: Option<String>,
+    title: Option<String>,
+    installed: Option<bool>,
+    stickers: Option<Vec<StickerInfoJson>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StickerInfoJson {
+    id: Option<u32>,
+    emoji: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub content_type: Option<String>,
+    // Where signal-cli saved the downloaded file, if we could work it out.
+    pub path: Option<PathBuf>,
+}
+
+/// A story posted by a contact (optionally to a group). Never persisted.
+#[derive(Debug, Clone)]
+pub struct Story {
+    pub author: String,
+    pub group_id: Option<String>,
+    pub timestamp_ms: i64,
+    pub text: Option<String>,
+    pub attachment: Option<Attachment>,
+    pub allows_replies: bool,
+}
+
+/// Someone is calling us. We cannot answer, but another of our devices may.
+#[derive(Debug, Clone)]
+pub struct CallEvent {
+    pub conversation_key: String,
+    pub source: Option<String>,
+    pub timestamp_ms: Option<i64>,
+    pub call_id: u64,
+    pub video: bool,
+}
+
+/// What a callMessage envelope says about a call, by call id.
+#[derive(Debug, Clone)]
+pub enum CallUpdate {
+    Offer(CallEvent),
+    // Picked up, here or on another of our devices.
+    Answered(u64),
+    // The caller hung up, or the call was busy.
+    Ended(u64),
+}
+
+/// Someone changed a chat's disappearing-message timer.
+#[derive(Debug, Clone)]
+pub struct ExpirationUpdate {
+    pub conversation_key: String,
+    pub source: Option<String>,
+    pub expires_in_secs: u32,
+}
+
+/// An envelope signal-cli could not decrypt because the sender's identity key
+/// is not trusted (new safety number / key change).
+#[derive(Debug, Clone)]
+pub struct IdentityAlert {
+    pub conversation_key: String,
+    pub source: Option<String>,
+    pub detail: String,
+}
+
+/// Everything one `receive` call produced that the UI cares about.
+#[derive(Debug, Clone, Default)]
+pub struct Received {
+    pub messages: Vec<IncomingMessage>,
+    pub identity_alerts: Vec<IdentityAlert>,
+    pub expiration_updates: Vec<ExpirationUpdate>,
+    pub stories: Vec<Story>,
+    pub calls: Vec<CallUpdate>,
+}
+
+impl Received {
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+            && self.identity_alerts.is_empty()
+            && self.expiration_updates.is_empty()
+            && self.stories.is_empty()
+            && self.calls.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub number: Option<String>,
+    pub uuid: Option<String>,
+    pub fingerprint: String,
+    pub safety_number: String,
+    // "TRUSTED_VERIFIED" | "TRUSTED_UNVERIFIED" | "UNTRUSTED"
+    pub trust_level: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdentityJson {
+    number: Option<String>,
+    uuid: Option<String>,
+    fingerprint: Option<String>,
+    #[serde(rename = "safetyNumber")]
+    safety_number: Option<String>,
+    #[serde(rename = "trustLevel")]
+    trust_level: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -79,6 +225,36 @@ impl SignalCli {
         Ok(accounts.into_iter().map(|a| a.number).collect())
     }
 
+    /// The account's own ACI (UUID), which mentions may use instead of the number:
+    /// from `listAccounts` where signal-cli reports it, else our own identity.
+    pub fn account_uuid(&self, account: &str) -> Result<Option<String>> {
+        #[derive(Debug, Deserialize)]
+        struct Account {
+            number: String,
+            #[serde(default)]
+            uuid: Option<String>,
+        }
+        if let Some(v) = self.run_json(["-o", "json", "listAccounts"])? {
+            let accounts: Vec<Account> = serde_json::from_value(v).context("parse listAccounts JSON")?;
+            if let Some(uuid) = accounts.into_iter().find(|a| a.number == account).and_then(|a| a.uuid) {
+                return Ok(Some(uuid));
+            }
+        }
+        Ok(self.list_identities(account, account)?.into_iter().find_map(|i| i.uuid))
+    }
+
+    /// Starts `signal-cli link`; it prints a `sgnl://linkdevice` URI on stdout and
+    /// exits once the primary device has scanned it.
+    pub fn spawn_link(&self, device_name: &str) -> Result<Child> {
+        Command::new(&self.bin)
+            .args(["link", "-n", device_name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to execute {}", self.bin))
+    }
+
     pub fn list_contacts(&self, account: &str) -> Result<Vec<Contact>> {
         // `--all-recipients` includes non-address-book recipients with profile names.
         let v = self.run_json([
@@ -132,33 +308,158 @@ impl SignalCli {
         Ok(out)
     }
 
-    pub fn send_message_to_number(&self, account: &str, recipient: &str, body: &str) -> Result<()> {
-        self.run_status(["-a", account, "send", "-m", body, recipient])
+    pub fn send_message_to_number(
+        &self,
+        account: &str,
+        recipient: &str,
+        body: &str,
+        styles: &[StyleRange],
+    ) -> Result<()> {
+        let mut args = vec!["-a".to_string(), account.to_string(), "send".to_string()];
+        args.extend(["-m".to_string(), body.to_string()]);
+        args.extend(text_style_args(styles));
+        args.push(recipient.to_string());
+        self.run_status(args)
             .with_context(|| format!("send message to {recipient}"))?;
         Ok(())
     }
 
-    pub fn send_message_to_group(&self, account: &str, group_id: &str, body: &str) -> Result<()> {
-        self.run_status(["-a", account, "send", "-g", group_id, "-m", body])
+    pub fn send_message_to_group(
+        &self,
+        account: &str,
+        group_id: &str,
+        body: &str,
+        styles: &[StyleRange],
+    ) -> Result<()> {
+        let mut args = vec!["-a".to_string(), account.to_string(), "send".to_string()];
+        args.extend(["-g".to_string(), group_id.to_string()]);
+        args.extend(["-m".to_string(), body.to_string()]);
+        args.extend(text_style_args(styles));
+        self.run_status(args)
             .with_context(|| format!("send message to group {group_id}"))?;
         Ok(())
     }
 
-    pub fn receive_once(&self, account: &str, timeout_secs: u64) -> Result<Vec<IncomingMessage>> {
+    pub fn list_identities(&self, account: &str, number: &str) -> Result<Vec<Identity>> {
+        let v = self.run_json(["-a", account, "-o", "json", "listIdentities", "-n", number])?;
+        let Some(v) = v else { return Ok(vec![]); };
+        let raw: Vec<IdentityJson> =
+            serde_json::from_value(v).context("parse listIdentities JSON")?;
+        let mut out = Vec::new();
+        for i in raw {
+            let Some(safety_number) = i.safety_number else { continue; };
+            out.push(Identity {
+                number: i.number,
+                uuid: i.uuid,
+                fingerprint: i.fingerprint.unwrap_or_default(),
+                safety_number,
+                trust_level: i.trust_level.unwrap_or_else(|| "UNKNOWN".to_string()),
+            });
+        }
+        Ok(out)
+    }
+
+    pub fn trust_verified(&self, account: &str, recipient: &str, safety_number: &str) -> Result<()> {
+        self.run_status(["-a", account, "trust", "-v", safety_number, recipient])
+            .with_context(|| format!("trust identity of {recipient}"))?;
+        Ok(())
+    }
+
+    pub fn list_sticker_packs(&self, account: &str) -> Result<Vec<StickerPack>> {
+        let v = self.run_json(["-a", account, "-o", "json", "listStickerPacks"])?;
+        let Some(v) = v else { return Ok(vec![]); };
+        let raw: Vec<StickerPackJson> =
+            serde_json::from_value(v).context("parse listStickerPacks JSON")?;
+        let mut out = Vec::new();
+        for p in raw {
+            let Some(pack_id) = p.pack_id else { continue; };
+            let stickers = p
+                .stickers
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|s| Some(StickerInfo { id: s.id?, emoji: s.emoji }))
+                .collect();
+            out.push(StickerPack {
+                pack_id,
+                title: p.title,
+                installed: p.installed.unwrap_or(false),
+                stickers,
+            });
+        }
+        Ok(out)
+    }
+
+    /// Replies to a story; the reply lands in the author's 1:1 chat.
+    pub fn send_story_reply(&self, account: &str, author: &str, story_ts_ms: i64, body: &str) -> Result<()> {
+        let ts = story_ts_ms.to_string();
+        self.run_status([
+            "-a",
+            account,
+            "send",
+            "--story-timestamp",
+            &ts,
+            "--story-author",
+            author,
+            "-m",
+            body,
+            author,
+        ])
+        .with_context(|| format!("reply to story from {author}"))?;
+        Ok(())
+    }
+
+    pub fn send_sticker_to_number(&self, account: &str, recipient: &str, sticker: &str) -> Result<()> {
+        self.run_status(["-a", account, "send", "--sticker", sticker, recipient])
+            .with_context(|| format!("send sticker to {recipient}"))?;
+        Ok(())
+    }
+
+    pub fn send_sticker_to_group(&self, account: &str, group_id: &str, sticker: &str) -> Result<()> {
+        self.run_status(["-a", account, "send", "-g", group_id, "--sticker", sticker])
+            .with_context(|| format!("send sticker to group {group_id}"))?;
+        Ok(())
+    }
+
+    /// The sticker's image file (WebP, sometimes PNG or APNG); signal-cli
+    /// prints it base64-encoded.
+    pub fn get_sticker(&self, account: &str, pack_id: &str, sticker_id: u32) -> Result<Vec<u8>> {
+        let id = sticker_id.to_string();
+        let out = self
+            .run_output(["-a", account, "getSticker", "--pack-id", pack_id, "--sticker-id", &id])
+            .with_context(|| format!("get sticker {pack_id}:{sticker_id}"))?;
+        let b64: Vec<u8> = out.into_iter().filter(|b| !b.is_ascii_whitespace()).collect();
+        BASE64.decode(b64).context("decode getSticker output")
+    }
+
+    pub fn set_expiration_timer_for_number(&self, account: &str, recipient: &str, secs: u32) -> Result<()> {
+        let secs = secs.to_string();
+        self.run_status(["-a", account, "updateContact", recipient, "-e", &secs])
+            .with_context(|| format!("set disappearing-message timer for {recipient}"))?;
+        Ok(())
+    }
+
+    pub fn set_expiration_timer_for_group(&self, account: &str, group_id: &str, secs: u32) -> Result<()> {
+        let secs = secs.to_string();
+        self.run_status(["-a", account, "updateGroup", "-g", group_id, "-e", &secs])
+            .with_context(|| format!("set disappearing-message timer for group {group_id}"))?;
+        Ok(())
+    }
+
+    pub fn receive_once(&self, account: &str, timeout_secs: u64) -> Result<Received> {
         let timeout = timeout_secs.to_string();
         let v = self.run_json(["-a", account, "-o", "json", "receive", "--timeout", &timeout])?;
-        let Some(v) = v else { return Ok(vec![]); };
+        let Some(v) = v else { return Ok(Received::default()); };
         self.parse_receive_json(v)
     }
 
-    fn parse_receive_json(&self, v: Value) -> Result<Vec<IncomingMessage>> {
+    fn parse_receive_json(&self, v: Value) -> Result<Received> {
         // `signal-cli -o json receive` format is not fully stable across versions; parse defensively.
         let items: Vec<Value> = match v {
             Value::Array(a) => a,
             other => vec![other],
         };
 
-        let mut out = Vec::new();
+        let mut out = Received::default();
         for item in items {
             let Some(obj) = item.as_object() else { continue; };
             let env = obj.get("envelope").unwrap_or(&Value::Null);
@@ -175,6 +476,85 @@ impl SignalCli {
                         .or_else(|| e.get("source").and_then(|s| s.as_str()))
                 })
                 .map(|s| s.to_string());
+            // Senders who hide their number only come with a UUID.
+            let source_uuid = env_obj
+                .and_then(|e| e.get("sourceUuid").and_then(|s| s.as_str()))
+                .map(|s| s.to_string());
+            let sender = source_number.or(source_uuid);
+
+            if let Some(exc) = obj.get("exception") {
+                // Undecryptable envelope; only identity problems are worth surfacing.
+                let ty = exc.get("type").and_then(|t| t.as_str()).unwrap_or("");
+                let detail = exc.get("message").and_then(|m| m.as_str()).unwrap_or(ty);
+                let untrusted = ty.contains("UntrustedIdentity")
+                    || detail.to_lowercase().contains("untrusted identity");
+                if untrusted && let Some(src) = &sender {
+                    out.identity_alerts.push(IdentityAlert {
+                        conversation_key: format!("contact:{src}"),
+                        source: Some(src.clone()),
+                        detail: detail.to_string(),
+                    });
+                }
+                continue;
+            }
+
+            if let Some(call) = env_obj.and_then(|e| e.get("callMessage")) {
+                // ICE updates carry nothing we show.
+                let id_of = |m: &Value| m.get("id").and_then(|i| i.as_u64());
+                if let Some(offer) = call.get("offerMessage")
+                    && let Some(src) = &sender
+                    && let Some(call_id) = id_of(offer)
+                {
+                    let video = offer
+                        .get("type")
+                        .and_then(|t| t.as_str())
+                        .is_some_and(|t| t.to_uppercase().contains("VIDEO"));
+                    out.calls.push(CallUpdate::Offer(CallEvent {
+                        conversation_key: format!("contact:{src}"),
+                        source: Some(src.clone()),
+                        timestamp_ms,
+                        call_id,
+                        video,
+                    }));
+                } else if let Some(id) = call.get("answerMessage").and_then(id_of) {
+                    // Also what our own devices sync when they pick up.
+                    out.calls.push(CallUpdate::Answered(id));
+                } else if let Some(hangup) = call.get("hangupMessage")
+                    && let Some(id) = id_of(hangup)
+                {
+                    // ACCEPTED/DECLINED: another of our devices took or rejected it.
+                    let ty = hangup.get("type").and_then(|t| t.as_str()).unwrap_or("").to_lowercase();
+                    out.calls.push(if ty == "accepted" || ty == "declined" {
+                        CallUpdate::Answered(id)
+                    } else {
+                        CallUpdate::Ended(id)
+                    });
+                } else if let Some(id) = call.get("busyMessage").and_then(id_of) {
+                    out.calls.push(CallUpdate::Ended(id));
+                }
+                continue;
+            }
+
+            if let Some(story) = env_obj.and_then(|e| e.get("storyMessage")) {
+                let (Some(author), Some(ts)) = (&sender, timestamp_ms) else { continue; };
+                let text = story
+                    .get("textAttachment")
+                    .and_then(|t| t.get("text"))
+                    .and_then(|t| t.as_str())
+                    .map(|t| t.to_string());
+                out.stories.push(Story {
+                    author: author.clone(),
+                    group_id: story.get("groupId").and_then(|g| g.as_str()).map(|g| g.to_string()),
+                    timestamp_ms: ts,
+                    text,
+                    attachment: story.get("fileAttachment").map(parse_attachment),
+                    allows_replies: story
+                        .get("allowsReplies")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(true),
+                });
+                continue;
+            }
 
             let data_msg = env_obj
                 .and_then(|e| e.get("dataMessage"))
@@ -186,10 +566,6 @@ impl SignalCli {
                 .and_then(|m| m.as_str())
                 .unwrap_or("")
                 .to_string();
-            if body.is_empty() {
-                // Ignore non-text events for now (typing, receipts, etc.)
-                continue;
-            }
 
             let group_id = data_msg
                 .get("groupInfo")
@@ -203,24 +579,94 @@ impl SignalCli {
 
             let conversation_key = if let Some(gid) = group_id {
                 format!("group:{gid}")
-            } else if let Some(src) = &source_number {
+            } else if let Some(src) = &sender {
                 format!("contact:{src}")
             } else {
                 // Unknown; keep it bucketed.
                 "unknown:unknown".to_string()
             };
 
-            out.push(IncomingMessage {
+            // 0 / missing means the chat has no disappearing-message timer.
+            let expires_in_secs = data_msg
+                .get("expiresInSeconds")
+                .and_then(|v| v.as_u64())
+                .map(|v| v.min(u32::MAX as u64) as u32)
+                .unwrap_or(0);
+            let is_expiration_update = data_msg
+                .get("isExpirationUpdate")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if is_expiration_update {
+                out.expiration_updates.push(ExpirationUpdate {
+                    conversation_key: conversation_key.clone(),
+                    source: sender.clone(),
+                    expires_in_secs,
+                });
+            }
+
+            let view_once = data_msg
+                .get("viewOnce")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let attachments: Vec<Attachment> = data_msg
+                .get("attachments")
+                .and_then(|a| a.as_array())
+                .map(|a| a.iter().map(parse_attachment).collect())
+                .unwrap_or_default();
+
+            let text_styles: Vec<StyleRange> = data_msg
+                .get("textStyles")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default();
+
+            let mentions: Vec<String> = data_msg
+                .get("mentions")
+                .and_then(|m| m.as_array())
+                .map(|a| {
+                    a.iter()
+                        .flat_map(|m| [m.get("number"), m.get("uuid")])
+                        .flatten()
+                        .filter_map(|v| v.as_str())
+                        .map(|v| v.to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let sticker = data_msg.get("sticker").and_then(|st| {
+                Some(Sticker {
+                    pack_id: st.get("packId")?.as_str()?.to_string(),
+                    sticker_id: st.get("stickerId")?.as_u64()? as u32,
+                    emoji: st.get("emoji").and_then(|e| e.as_str()).map(|e| e.to_string()),
+                })
+            });
+
+            let has_view_once_media = view_once && !attachments.is_empty();
+            if body.is_empty() && !has_view_once_media && sticker.is_none() {
+                // Ignore non-text events for now (typing, receipts, etc.)
+                continue;
+            }
+
+            out.messages.push(IncomingMessage {
                 conversation_key,
-                source: source_number,
+                source: sender,
                 timestamp_ms,
                 body,
+                expires_in_secs,
+                view_once,
+                attachments,
+                sticker,
+                text_styles,
+                mentions,
             });
         }
         Ok(out)
     }
 
-    fn run_status<const N: usize>(&self, args: [&str; N]) -> Result<()> {
+    fn run_status<I, S>(&self, args: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
         let mut cmd = Command::new(&self.bin);
         cmd.args(args);
         let output = cmd
@@ -239,6 +685,18 @@ impl SignalCli {
         );
     }
 
+    fn run_output<const N: usize>(&self, args: [&str; N]) -> Result<Vec<u8>> {
+        let output = Command::new(&self.bin)
+            .args(args)
+            .output()
+            .with_context(|| format!("failed to execute {}", self.bin))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("signal-cli failed (code={:?}). stderr: {}", output.status.code(), stderr.trim());
+        }
+        Ok(output.stdout)
+    }
+
     fn run_json<const N: usize>(&self, args: [&str; N]) -> Result<Option<Value>> {
         let mut cmd = Command::new(&self.bin);
         cmd.args(args);
@@ -277,6 +735,40 @@ impl SignalCli {
     }
 }
 
+fn text_style_args(styles: &[StyleRange]) -> Vec<String> {
+    styles
+        .iter()
+        .flat_map(|s| ["--text-style".to_string(), s.to_arg()])
+        .collect()
+}
+
+fn parse_attachment(v: &Value) -> Attachment {
+    let s = |k: &str| v.get(k).and_then(|x| x.as_str()).map(|x| x.to_string());
+    // Newer signal-cli reports the stored file name; older ones only the id.
+    let stored = s("storedFilename").or_else(|| s("id"));
+    let path = stored.and_then(|f| {
+        let p = PathBuf::from(&f);
+        if p.is_absolute() {
+            Some(p)
+        } else {
+            attachments_dir().map(|d| d.join(f))
+        }
+    });
+    Attachment {
+        content_type: s("contentType"),
+        path,
+    }
+}
+
+fn attachments_dir() -> Option<PathBuf> {
+    let base = if let Some(xdg) = std::env::var_os("XDG_DATA_HOME") {
+        PathBuf::from(xdg)
+    } else {
+        PathBuf::from(std::env::var_os("HOME")?).join(".local").join("share")
+    };
+    Some(base.join("signal-cli").join("attachments"))
+}
+
 fn best_contact_name(c: &ContactJson) -> Option<String> {
     // Prefer explicit local contact name, then OS-style fields, then profile.
     fn clean(s: &str) -> Option<String> {
@@ -303,3 +795,44 @@ fn best_contact_name(c: &ContactJson) -> Option<String> {
             join2(&p.given_name, &p.family_name)
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID: &str = "6e8a5c3e-1f0b-4d7a-9c1e-2b3d4f5a6b7c";
+
+    fn parse(envelope: Value) -> Received {
+        SignalCli::default().parse_receive_json(serde_json::json!([{ "envelope": envelope }])).unwrap()
+    }
+
+    #[test]
+    fn call_offers_from_a_hidden_number_use_the_uuid() {
+        let rx = parse(serde_json::json!({
+            "sourceUuid": UUID,
+            "timestamp": 1000,
+            "callMessage": { "offerMessage": { "id": 42, "type": "VIDEO_CALL" } },
+        }));
+        let [CallUpdate::Offer(call)] = rx.calls.as_slice() else { panic!("{:?}", rx.calls) };
+        assert_eq!(call.conversation_key, format!("contact:{UUID}"));
+        assert_eq!(call.source.as_deref(), Some(UUID));
+        assert_eq!((call.call_id, call.video), (42, true));
+    }
+
+    #[test]
+    fn messages_prefer_the_number_over_the_uuid() {
+        let rx = parse(serde_json::json!({
+            "sourceNumber": "+15550000002",
+            "sourceUuid": UUID,
+            "timestamp": 1000,
+            "dataMessage": { "message": "hi" },
+        }));
+        assert_eq!(rx.messages[0].conversation_key, "contact:+15550000002");
+        let rx = parse(serde_json::json!({
+            "sourceUuid": UUID,
+            "timestamp": 1000,
+            "dataMessage": { "message": "hi" },
+        }));
+        assert_eq!(rx.messages[0].conversation_key, format!("contact:{UUID}"));
+    }
+}
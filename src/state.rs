@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::notify::ChatRule;
+
+const DRAFTS: &str = "drafts.json";
+const PINNED: &str = "pinned.json";
+const ARCHIVED: &str = "archived.json";
+const NOTIFY_RULES: &str = "notify_rules.json";
+const TIMERS: &str = "timers.json";
+
+/// Unsent drafts of `account`, keyed by conversation key.
+pub fn load_drafts(state_dir: &Path, account: &str) -> HashMap<String, String> {
+    load(state_dir, DRAFTS, account)
+}
+
+/// Replaces the stored drafts of `account` (other accounts are kept).
+pub fn save_drafts(state_dir: &Path, account: &str, drafts: &HashMap<String, String>) -> Result<()> {
+    save(state_dir, DRAFTS, account, drafts, drafts.is_empty()).context("save drafts")
+}
+
+/// Conversation keys `account` pinned to the top of the chat list.
+pub fn load_pinned(state_dir: &Path, account: &str) -> Vec<String> {
+    load(state_dir, PINNED, account)
+}
+
+pub fn save_pinned(state_dir: &Path, account: &str, pinned: &[String]) -> Result<()> {
+    save(state_dir, PINNED, account, &pinned, pinned.is_empty()).context("save pinned chats")
+}
+
+/// Conversation keys `account` archived out of the chat list.
+pub fn load_archived(state_dir: &Path, account: &str) -> Vec<String> {
+    load(state_dir, ARCHIVED, account)
+}
+
+pub fn save_archived(state_dir: &Path, account: &str, archived: &[String]) -> Result<()> {
+    save(state_dir, ARCHIVED, account, &archived, archived.is_empty()).context("save archived chats")
+}
+
+/// Notification settings changed from the TUI, keyed by conversation key.
+pub fn load_notify_rules(state_dir: &Path, account: &str) -> HashMap<String, ChatRule> {
+    load(state_dir, NOTIFY_RULES, account)
+}
+
+pub fn save_notify_rules(state_dir: &Path, account: &str, rules: &HashMap<String, ChatRule>) -> Result<()> {
+    save(state_dir, NOTIFY_RULES, account, rules, rules.is_empty()).context("save notification settings")
+}
+
+/// Disappearing-message timers in seconds, keyed by conversation key (off = absent).
+pub fn load_timers(state_dir: &Path, account: &str) -> HashMap<String, u32> {
+    load(state_dir, TIMERS, account)
+}
+
+pub fn save_timers(state_dir: &Path, account: &str, timers: &HashMap<String, u32>) -> Result<()> {
+    save(state_dir, TIMERS, account, timers, timers.is_empty()).context("save disappearing timers")
+}
+
+// State files hold one entry per account: {"+15551234567": <value>, ...}.
+fn read_all(state_dir: &Path, file: &str) -> HashMap<String, serde_json::Value> {
+    // A missing or broken file just means nothing saved yet.
+    fs::read_to_string(state_dir.join(file))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn load<T: DeserializeOwned + Default>(state_dir: &Path, file: &str, account: &str) -> T {
+    read_all(state_dir, file)
+        .remove(account)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default()
+}
+
+fn save<T: Serialize>(state_dir: &Path, file: &str, account: &str, value: &T, empty: bool) -> Result<()> {
+    let mut all = read_all(state_dir, file);
+    if empty {
+        all.remove(account);
+    } else {
+        all.insert(account.to_string(), serde_json::to_value(value).context("serialize state")?);
+    }
+    let json = serde_json::to_string_pretty(&all).context("serialize state")?;
+    write_private(&state_dir.join(file), json.as_bytes())
+}
+
+/// Write-then-rename with 0600 permissions; drafts and history are as private as messages.
+pub fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut f = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)
+        .with_context(|| format!("create {tmp:?}"))?;
+    f.write_all(data).with_context(|| format!("write {tmp:?}"))?;
+    fs::rename(&tmp, path).with_context(|| format!("replace {path:?}"))?;
+    Ok(())
+}
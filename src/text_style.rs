@@ -0,0 +1,242 @@
+use ratatui::{
+    style::{Modifier, Style},
+    text::Span,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::theme::Theme;
+
+/// A Signal text style range. `start`/`length` count UTF-16 code units, like the
+/// Signal protocol (and signal-cli's `--text-style start:length:STYLE`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StyleRange {
+    pub start: usize,
+    pub length: usize,
+    // "BOLD" | "ITALIC" | "STRIKETHROUGH" | "MONOSPACE" | "SPOILER"
+    pub style: String,
+}
+
+impl StyleRange {
+    /// The `--text-style` argument signal-cli expects.
+    pub fn to_arg(&self) -> String {
+        format!("{}:{}:{}", self.start, self.length, self.style)
+    }
+}
+
+// Composer markup -> Signal style. Longest delimiters first.
+const MARKUP: [(&str, &str); 5] = [
+    ("||", "SPOILER"),
+    ("*", "BOLD"),
+    ("_", "ITALIC"),
+    ("~", "STRIKETHROUGH"),
+    ("`", "MONOSPACE"),
+];
+
+/// Strips `*bold*`, `_italic_`, `~strike~`, `` `mono` `` and `||spoiler||` markup
+/// from composer text and returns the plain text plus its style ranges.
+/// Delimiters without a partner (e.g. `2 * 3`) are left alone, and so are
+/// delimiters inside a word (`snake_case_name`, `foo_bar` in URLs): an opener
+/// must start the text or follow whitespace/punctuation, a closer must end it
+/// or be followed by whitespace/punctuation.
+pub fn parse_markup(input: &str) -> (String, Vec<StyleRange>) {
+    let cs: Vec<char> = input.chars().collect();
+    let n = cs.len();
+    let delim_at = |i: usize| -> Option<(&'static str, &'static str)> {
+        MARKUP.iter().copied().find(|(d, _)| {
+            let d: Vec<char> = d.chars().collect();
+            i + d.len() <= n && cs[i..i + d.len()] == d[..]
+        })
+    };
+
+    // Pass 1: pair up delimiters. `skip[i]` = delimiter length to drop at char i.
+    let mut skip = vec![0usize; n];
+    let mut pairs = Vec::new();
+    let mut i = 0;
+    while i < n {
+        if skip[i] > 0 {
+            i += skip[i];
+            continue;
+        }
+        let Some((d, style)) = delim_at(i) else {
+            i += 1;
+            continue;
+        };
+        let dl = d.chars().count();
+        let after = i + dl;
+        let boundary = |k: usize| k >= n || !cs[k].is_alphanumeric();
+        if (i > 0 && !boundary(i - 1)) || after >= n || cs[after].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let closer = (after + 1..n).find(|&k| {
+            skip[k] == 0
+                && delim_at(k).is_some_and(|(x, _)| x == d)
+                && !cs[k - 1].is_whitespace()
+                && boundary(k + dl)
+        });
+        let Some(j) = closer else {
+            i += 1;
+            continue;
+        };
+        skip[i] = dl;
+        skip[j] = dl;
+        pairs.push((i, j, style));
+        // Monospace content is literal; everything else may nest.
+        i = if style == "MONOSPACE" { j + dl } else { after };
+    }
+
+    // Pass 2: emit text, remembering the UTF-16 offset at every char index.
+    let mut out = String::with_capacity(input.len());
+    let mut pos = vec![0usize; n + 1];
+    let mut off = 0;
+    let mut i = 0;
+    while i < n {
+        if skip[i] > 0 {
+            pos[i..i + skip[i]].fill(off);
+            i += skip[i];
+            continue;
+        }
+        pos[i] = off;
+        out.push(cs[i]);
+        off += cs[i].len_utf16();
+        i += 1;
+    }
+    pos[n] = off;
+
+    let mut ranges: Vec<StyleRange> = pairs
+        .into_iter()
+        .filter_map(|(open, close, style)| {
+            let start = pos[open];
+            let length = pos[close] - start;
+            (length > 0).then(|| StyleRange { start, length, style: style.to_string() })
+        })
+        .collect();
+    ranges.sort_by_key(|r| r.start);
+    (out, ranges)
+}
+
+/// Renders `body` with its style ranges, monospace and masked spoilers in the
+/// theme's styles. Spoilers are masked unless `reveal`.
+pub fn styled_spans(body: &str, ranges: &[StyleRange], reveal: bool, theme: &Theme) -> Vec<Span<'static>> {
+    if ranges.is_empty() {
+        return vec![Span::raw(body.to_string())];
+    }
+
+    let mut spans = Vec::new();
+    let mut cur = String::new();
+    let mut cur_style = Style::default();
+    let mut off = 0;
+    for c in body.chars() {
+        let mut style = Style::default();
+        let mut spoiler = false;
+        for r in ranges.iter().filter(|r| off >= r.start && off < r.start + r.length) {
+            style = match r.style.as_str() {
+                "BOLD" => style.add_modifier(Modifier::BOLD),
+                "ITALIC" => style.add_modifier(Modifier::ITALIC),
+                "STRIKETHROUGH" => style.add_modifier(Modifier::CROSSED_OUT),
+                // No monospace in a terminal (it already is); set it apart instead.
+                "MONOSPACE" => style.patch(theme.monospace),
+                "SPOILER" => {
+                    spoiler = true;
+                    style
+                }
+                _ => style,
+            };
+        }
+        if spoiler && !reveal {
+            style = style.patch(theme.spoiler);
+        }
+        if style != cur_style && !cur.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut cur), cur_style));
+        }
+        cur_style = style;
+        cur.push(if spoiler && !reveal && !c.is_whitespace() { '▒' } else { c });
+        off += c.len_utf16();
+    }
+    if !cur.is_empty() {
+        spans.push(Span::styled(cur, cur_style));
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: usize, length: usize, style: &str) -> StyleRange {
+        StyleRange { start, length, style: style.to_string() }
+    }
+
+    #[test]
+    fn plain_delimiters_inside_words_are_text() {
+        for text in ["snake_case_name", "a*b*c", "x~y~z", "call my_func_name() now"] {
+            assert_eq!(parse_markup(text), (text.to_string(), vec![]), "{text}");
+        }
+    }
+
+    #[test]
+    fn urls_and_paths_are_untouched() {
+        for text in [
+            "see https://example.com/foo_bar_baz/x~y~z",
+            "https://example.com/a_b_c and https://example.com/d_e",
+            "cd ~/src/my_project_dir",
+        ] {
+            assert_eq!(parse_markup(text), (text.to_string(), vec![]), "{text}");
+        }
+    }
+
+    #[test]
+    fn unpaired_and_spaced_delimiters_are_text() {
+        assert_eq!(parse_markup("2 * 3 = 6"), ("2 * 3 = 6".to_string(), vec![]));
+        assert_eq!(parse_markup("2 * 3 * 4"), ("2 * 3 * 4".to_string(), vec![]));
+        assert_eq!(parse_markup("*not bold *"), ("*not bold *".to_string(), vec![]));
+    }
+
+    #[test]
+    fn word_markup() {
+        assert_eq!(parse_markup("a *bold* word"), ("a bold word".to_string(), vec![range(2, 4, "BOLD")]));
+        assert_eq!(parse_markup("(_it_), ok"), ("(it), ok".to_string(), vec![range(1, 2, "ITALIC")]));
+        assert_eq!(parse_markup("~gone~."), ("gone.".to_string(), vec![range(0, 4, "STRIKETHROUGH")]));
+        assert_eq!(parse_markup("||secret||!"), ("secret!".to_string(), vec![range(0, 6, "SPOILER")]));
+    }
+
+    #[test]
+    fn nested_markup() {
+        assert_eq!(
+            parse_markup("*bold _both_*"),
+            ("bold both".to_string(), vec![range(0, 9, "BOLD"), range(5, 4, "ITALIC")])
+        );
+        assert_eq!(
+            parse_markup("_*both*_ and `*lit*`"),
+            (
+                "both and *lit*".to_string(),
+                vec![range(0, 4, "ITALIC"), range(0, 4, "BOLD"), range(9, 5, "MONOSPACE")]
+            )
+        );
+    }
+
+    #[test]
+    fn offsets_count_utf16() {
+        assert_eq!(parse_markup("😀 *hi*"), ("😀 hi".to_string(), vec![range(3, 2, "BOLD")]));
+    }
+
+    #[test]
+    fn spans_take_monospace_and_spoiler_styles_from_the_theme() {
+        let ranges = [range(0, 4, "MONOSPACE"), range(5, 3, "SPOILER")];
+        let theme = Theme::high_contrast();
+        let spans = styled_spans("code abc", &ranges, false, &theme);
+        assert_eq!(spans[0].content, "code");
+        assert_eq!(spans[0].style, theme.monospace);
+        assert_eq!(spans[2].content, "▒▒▒");
+        assert_eq!(spans[2].style, theme.spoiler);
+        // Revealed spoilers are plain text.
+        let spans = styled_spans("code abc", &ranges, true, &theme);
+        assert_eq!(spans.last().unwrap().content, " abc");
+
+        // NO_COLOR: no colors on either.
+        let plain = Theme::from_config(&Default::default(), &Default::default(), true).unwrap();
+        for span in styled_spans("code abc", &ranges, false, &plain) {
+            assert_eq!((span.style.fg, span.style.bg), (None, None));
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use ratatui::style::{Color, Modifier, Style};
+
+/// Named UI styles. Built-in themes can be adjusted style by style in `[theme]`.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    // Highlighted row in lists.
+    pub selection: Style,
+    // Chats with unread messages, unread counters.
+    pub unread: Style,
+    // "<ts> < who:" prefix of incoming messages.
+    pub incoming: Style,
+    // "<ts> > me:" prefix of sent messages.
+    pub outgoing: Style,
+    // Generated lines (missed calls, ...).
+    pub system: Style,
+    // Account label and prompt in the status bar.
+    pub status: Style,
+    pub border: Style,
+    // Secondary text: timestamps, labels, drafts.
+    pub dim: Style,
+    // Search hits, hints that need attention.
+    pub highlight: Style,
+    // Safety-number changes, untrusted identities.
+    pub warning: Style,
+    // `monospace` text in messages (the terminal already is monospace).
+    pub monospace: Style,
+    // Masked spoilers, until revealed.
+    pub spoiler: Style,
+    // Sender names are colored from this palette (empty: no colors).
+    pub sender_palette: Vec<Color>,
+    // Number or UUID -> color, from `[sender_colors]`.
+    pub sender_overrides: HashMap<String, Color>,
+    // NO_COLOR: the UI strips every color before drawing.
+    pub no_color: bool,
+}
+
+const STYLE_NAMES: [&str; 12] = [
+    "selection",
+    "unread",
+    "incoming",
+    "outgoing",
+    "system",
+    "status",
+    "border",
+    "dim",
+    "highlight",
+    "warning",
+    "monospace",
+    "spoiler",
+];
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            selection: Style::default().fg(Color::Black).bg(Color::LightGreen).add_modifier(Modifier::BOLD),
+            unread: Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            incoming: Style::default().fg(Color::Gray),
+            outgoing: Style::default().fg(Color::Green),
+            system: Style::default().fg(Color::Yellow).add_modifier(Modifier::ITALIC),
+            status: Style::default().fg(Color::Cyan),
+            border: Style::default(),
+            dim: Style::default().fg(Color::DarkGray),
+            highlight: Style::default().fg(Color::Black).bg(Color::Yellow),
+            warning: Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            // Bright colors only; dark ones vanish on a dark background.
+            monospace: Style::default().fg(Color::LightCyan),
+            spoiler: Style::default().fg(Color::DarkGray),
+            sender_palette: vec![
+                Color::LightRed,
+                Color::LightGreen,
+                Color::LightYellow,
+                Color::LightBlue,
+                Color::LightMagenta,
+                Color::LightCyan,
+                Color::Indexed(208),
+                Color::Indexed(141),
+                Color::Indexed(114),
+                Color::Indexed(117),
+            ],
+            sender_overrides: HashMap::new(),
+            no_color: false,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            selection: Style::default().fg(Color::White).bg(Color::Blue).add_modifier(Modifier::BOLD),
+            unread: Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+            incoming: Style::default().fg(Color::DarkGray),
+            outgoing: Style::default().fg(Color::Blue),
+            system: Style::default().fg(Color::Magenta).add_modifier(Modifier::ITALIC),
+            status: Style::default().fg(Color::Blue),
+            border: Style::default().fg(Color::DarkGray),
+            dim: Style::default().fg(Color::DarkGray),
+            highlight: Style::default().fg(Color::Black).bg(Color::LightYellow),
+            warning: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            // Dark colors only; yellow and light shades wash out on white.
+            monospace: Style::default().fg(Color::Indexed(24)),
+            spoiler: Style::default().fg(Color::Gray),
+            sender_palette: vec![
+                Color::Red,
+                Color::Green,
+                Color::Blue,
+                Color::Magenta,
+                Color::Cyan,
+                Color::Indexed(130),
+                Color::Indexed(54),
+                Color::Indexed(22),
+                Color::Indexed(24),
+                Color::Indexed(125),
+            ],
+            sender_overrides: HashMap::new(),
+            no_color: false,
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            selection: Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD),
+            unread: Style::default().fg(Color::White).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            incoming: Style::default().fg(Color::White),
+            outgoing: Style::default().fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+            system: Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD | Modifier::ITALIC),
+            status: Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+            border: Style::default().fg(Color::White),
+            dim: Style::default().fg(Color::White),
+            highlight: Style::default().fg(Color::Black).bg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            warning: Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            monospace: Style::default().fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+            spoiler: Style::default().fg(Color::White),
+            sender_palette: vec![
+                Color::LightRed,
+                Color::LightGreen,
+                Color::LightYellow,
+                Color::LightBlue,
+                Color::LightMagenta,
+                Color::LightCyan,
+            ],
+            sender_overrides: HashMap::new(),
+            no_color: false,
+        }
+    }
+
+    /// Attributes only, for `NO_COLOR` (https://no-color.org).
+    pub fn no_color() -> Self {
+        Self {
+            selection: Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            unread: Style::default().add_modifier(Modifier::BOLD),
+            incoming: Style::default(),
+            outgoing: Style::default(),
+            system: Style::default().add_modifier(Modifier::ITALIC),
+            status: Style::default().add_modifier(Modifier::BOLD),
+            border: Style::default(),
+            dim: Style::default(),
+            highlight: Style::default().add_modifier(Modifier::REVERSED),
+            warning: Style::default().add_modifier(Modifier::BOLD),
+            monospace: Style::default(),
+            spoiler: Style::default(),
+            sender_palette: Vec::new(),
+            sender_overrides: HashMap::new(),
+            no_color: true,
+        }
+    }
+
+    /// Builds the theme from `[theme]`: `name` picks a built-in theme, the
+    /// other keys override single styles. `sender_colors` maps numbers/UUIDs to
+    /// colors. `NO_COLOR` wins over all of them.
+    pub fn from_config(
+        raw: &HashMap<String, String>,
+        sender_colors: &HashMap<String, String>,
+        no_color: bool,
+    ) -> Result<Self> {
+        let mut theme = match raw.get("name").map(|s| s.trim()) {
+            None | Some("dark") => Self::dark(),
+            Some("light") => Self::light(),
+            Some("high-contrast") => Self::high_contrast(),
+            Some(other) => bail!("unknown theme {other:?} (expected dark, light or high-contrast)"),
+        };
+        for (key, spec) in raw {
+            if key == "name" {
+                continue;
+            }
+            let style = parse_style(spec).with_context(|| format!("[theme] {key}"))?;
+            let slot = match key.as_str() {
+                "selection" => &mut theme.selection,
+                "unread" => &mut theme.unread,
+                "incoming" => &mut theme.incoming,
+                "outgoing" => &mut theme.outgoing,
+                "system" => &mut theme.system,
+                "status" => &mut theme.status,
+                "border" => &mut theme.border,
+                "dim" => &mut theme.dim,
+                "highlight" => &mut theme.highlight,
+                "warning" => &mut theme.warning,
+                "monospace" => &mut theme.monospace,
+                "spoiler" => &mut theme.spoiler,
+                _ => bail!("unknown [theme] style {key:?} (expected name or one of: {})", STYLE_NAMES.join(", ")),
+            };
+            *slot = style;
+        }
+        for (id, color) in sender_colors {
+            let color = parse_color(color.trim()).with_context(|| format!("[sender_colors] {id:?}"))?;
+            theme.sender_overrides.insert(id.clone(), color);
+        }
+        if no_color {
+            // Keep the attributes of the chosen theme; drop its colors.
+            let base = Self::no_color();
+            theme = Self {
+                selection: strip(theme.selection).patch(base.selection),
+                unread: strip(theme.unread).patch(base.unread),
+                incoming: strip(theme.incoming),
+                outgoing: strip(theme.outgoing),
+                system: strip(theme.system).patch(base.system),
+                status: strip(theme.status).patch(base.status),
+                border: strip(theme.border),
+                dim: strip(theme.dim),
+                highlight: strip(theme.highlight).patch(base.highlight),
+                warning: strip(theme.warning).patch(base.warning),
+                monospace: strip(theme.monospace),
+                spoiler: strip(theme.spoiler),
+                sender_palette: Vec::new(),
+                sender_overrides: HashMap::new(),
+                no_color: true,
+            };
+        }
+        Ok(theme)
+    }
+
+    /// Style for a sender's name: the override if configured, otherwise a
+    /// palette color picked by a stable hash of the number/UUID, so a person
+    /// looks the same in every chat and across restarts.
+    pub fn sender(&self, id: &str) -> Style {
+        if let Some(&c) = self.sender_overrides.get(id) {
+            return Style::default().fg(c);
+        }
+        if self.sender_palette.is_empty() {
+            return Style::default().add_modifier(Modifier::BOLD);
+        }
+        // FNV-1a: fixed across builds, unlike std's hasher.
+        let hash = id.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        let color = self.sender_palette[(hash % self.sender_palette.len() as u64) as usize];
+        Style::default().fg(color)
+    }
+}
+
+fn strip(style: Style) -> Style {
+    Style { fg: None, bg: None, ..style }
+}
+
+/// Parses "fg [on bg] [bold] [italic] ...", e.g. "black on lightgreen bold",
+/// "#ffaa00", "on blue", "reversed". Colors are names, 0-255 or #rrggbb.
+fn parse_style(spec: &str) -> Result<Style> {
+    let mut style = Style::default();
+    let mut words = spec.split_whitespace().peekable();
+    while let Some(w) = words.next() {
+        let lower = w.to_ascii_lowercase();
+        let modifier = match lower.as_str() {
+            "bold" => Some(Modifier::BOLD),
+            "dim" => Some(Modifier::DIM),
+            "italic" => Some(Modifier::ITALIC),
+            "underlined" | "underline" => Some(Modifier::UNDERLINED),
+            "reversed" | "reverse" => Some(Modifier::REVERSED),
+            "crossed-out" | "strikethrough" => Some(Modifier::CROSSED_OUT),
+            _ => None,
+        };
+        if let Some(m) = modifier {
+            style = style.add_modifier(m);
+        } else if lower == "on" {
+            let bg = words.next().context("missing color after \"on\"")?;
+            style = style.bg(parse_color(bg)?);
+        } else {
+            style = style.fg(parse_color(w)?);
+        }
+    }
+    Ok(style)
+}
+
+fn parse_color(s: &str) -> Result<Color> {
+    Color::from_str(s).map_err(|_| anyhow::anyhow!("unknown color {s:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn theme(entries: &[(&str, &str)]) -> Result<Theme> {
+        let raw = entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Theme::from_config(&raw, &HashMap::new(), false)
+    }
+
+    #[test]
+    fn parse_style_reads_colors_and_attributes() {
+        assert_eq!(
+            parse_style("black on lightgreen bold").unwrap(),
+            Style::default().fg(Color::Black).bg(Color::LightGreen).add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(parse_style("#ffaa00").unwrap(), Style::default().fg(Color::Rgb(0xff, 0xaa, 0x00)));
+        assert_eq!(parse_style("on 208").unwrap(), Style::default().bg(Color::Indexed(208)));
+        assert_eq!(parse_style("0 on 255").unwrap(), Style::default().fg(Color::Indexed(0)).bg(Color::Indexed(255)));
+        assert_eq!(
+            parse_style("Italic underline").unwrap(),
+            Style::default().add_modifier(Modifier::ITALIC | Modifier::UNDERLINED)
+        );
+    }
+
+    #[test]
+    fn parse_style_rejects_bad_specs() {
+        assert!(parse_style("black on").unwrap_err().to_string().contains("missing color"));
+        assert!(parse_style("blurple").is_err());
+        assert!(parse_style("#12345").is_err());
+        assert!(parse_style("256").is_err());
+    }
+
+    #[test]
+    fn config_picks_a_theme_and_overrides_styles() {
+        let t = theme(&[("name", "light"), ("warning", "red on white")]).unwrap();
+        assert_eq!(t.warning, Style::default().fg(Color::Red).bg(Color::White));
+        assert_eq!(t.selection, Theme::light().selection);
+
+        let err = theme(&[("name", "solarized")]).unwrap_err();
+        assert!(format!("{err:#}").contains("unknown theme"), "{err:#}");
+        let err = theme(&[("selected", "red")]).unwrap_err();
+        assert!(format!("{err:#}").contains("unknown [theme] style \"selected\""), "{err:#}");
+        let err = theme(&[("dim", "on")]).unwrap_err();
+        assert!(format!("{err:#}").contains("[theme] dim"), "{err:#}");
+    }
+
+    #[test]
+    fn no_color_keeps_attributes_only() {
+        let raw = HashMap::from([("selection".to_string(), "white on blue bold".to_string())]);
+        let colors = HashMap::from([("+15551234567".to_string(), "red".to_string())]);
+        let t = Theme::from_config(&raw, &colors, true).unwrap();
+        assert!(t.no_color);
+        assert_eq!(t.selection.fg, None);
+        assert_eq!(t.selection.bg, None);
+        assert!(t.selection.add_modifier.contains(Modifier::BOLD | Modifier::REVERSED));
+        assert_eq!(t.sender("+15551234567"), Style::default().add_modifier(Modifier::BOLD));
+    }
+}